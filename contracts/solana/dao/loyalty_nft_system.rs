@@ -1,15 +1,74 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{Token, TokenAccount, Mint, self, Transfer, MintTo, SetAuthority};
+use anchor_lang::system_program;
+use anchor_spl::token::{Token, TokenAccount, Mint, self, Transfer, MintTo, SetAuthority, Approve};
 use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::metadata::{
+    self as token_metadata, BurnNft, CreateMasterEditionV3, CreateMetadataAccountsV3, FreezeDelegatedAccount,
+    Metadata, SetAndVerifySizedCollectionItem, ThawDelegatedAccount, UpdateMetadataAccountsV2,
+    mpl_token_metadata::types::{Collection as MetadataCollection, CollectionDetails, DataV2},
+};
 use spl_token::instruction::AuthorityType;
+use mpl_bubblegum::instructions::{CreateTreeConfigCpiBuilder, MintToCollectionV1CpiBuilder};
+use mpl_bubblegum::types::{
+    Collection as BubblegumCollection, LeafSchema, MetadataArgs, TokenProgramVersion, TokenStandard,
+};
+use spl_account_compression::{program::SplAccountCompression, Noop};
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::invoke_signed;
+use anchor_lang::solana_program::bpf_loader_upgradeable;
 
 declare_id!("81y1B91W78o5zLz6Lg8P96Y7JvW4Y9q6D8W2o7Jz8K9"); // Replace with your program ID
 
-/// An enum to define the two types of NFTs.
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+/// $RAC uses 9 decimals; supply constants below are in base units.
+pub const RAC_DECIMALS: u8 = 9;
+/// Hard cap on $RAC issuance: 1.5 billion tokens.
+pub const RAC_MAX_SUPPLY: u64 = 1_500_000_000 * 10u64.pow(RAC_DECIMALS as u32);
+/// Initial $RAC mint to the DAO vault: 100 million tokens.
+pub const RAC_INITIAL_SUPPLY: u64 = 100_000_000 * 10u64.pow(RAC_DECIMALS as u32);
+
+/// Metaplex limits on metadata string lengths.
+pub const MAX_METADATA_NAME_LEN: usize = 32;
+pub const MAX_METADATA_SYMBOL_LEN: usize = 10;
+pub const MAX_METADATA_URI_LEN: usize = 200;
+
+/// Layout version stored in the leading `version` byte of every program account.
+/// Accounts written before the byte existed are migrated with the `migrate_*` instructions.
+pub const ACCOUNT_VERSION: u8 = 1;
+
+/// Fixed-point scale for the per-share passive income accumulator.
+pub const INCOME_PRECISION: u128 = 1_000_000_000_000;
+/// Income rates are annual basis points; accrual is prorated per second.
+pub const SECONDS_PER_YEAR: i64 = 365 * 24 * 60 * 60;
+/// Earn-on-spend caps reset every UTC day.
+pub const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+/// Longest merchant name stored on-chain.
+pub const MAX_MERCHANT_NAME_LEN: usize = 64;
+/// A merchant may credit this many customers' `max_rewards_per_user` per day, all customers together.
+pub const MERCHANT_DAILY_CAP_MULTIPLIER: u64 = 100;
+/// Most card tiers `initialize_catalog` accepts in one call. Only tiers with short names and
+/// URIs fit this many into one transaction; `CardTierSpec::catalog_batches` splits by size.
+pub const MAX_CATALOG_SPECS: usize = 6;
+/// Largest serialized transaction the cluster accepts.
+pub const MAX_TRANSACTION_SIZE: usize = 1232;
+/// Bytes of an `initialize_catalog` transaction besides its specs: one signature, the admin,
+/// system program and program keys, the blockhash, and the instruction header and data prefix.
+pub const CATALOG_TRANSACTION_OVERHEAD: usize = (1 + 64) + 3 + (1 + 3 * 32) + 32 + (1 + 1 + 1 + 2 + 2) + 8 + 4;
+
+/// Shortest allowed veRAC lock (1 week).
+pub const MIN_LOCK_DURATION: i64 = 7 * 24 * 60 * 60;
+/// Longest allowed veRAC lock (4 years). A lock this long votes with its full amount.
+pub const MAX_LOCK_DURATION: i64 = 4 * 365 * 24 * 60 * 60;
+/// Number of DAO orgs a proposal can belong to (`dao_org` 0-4).
+pub const DAO_ORG_COUNT: u8 = 5;
+/// Limits on a single instruction stored in a proposal.
+pub const MAX_INSTRUCTION_ACCOUNTS: usize = 32;
+pub const MAX_INSTRUCTION_DATA_LEN: usize = 1024;
+
+/// An enum to define the two types of NFTs. The discriminant is part of the NFT PDA seeds.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
 pub enum CustodyType {
-    NonCustodial,
-    Custodial,
+    NonCustodial,   // 0 - Bought by external wallet users
+    Custodial,      // 1 - Free for platform wallet users
 }
 
 /// An enum to define proposal types.
@@ -20,6 +79,7 @@ pub enum ProposalType {
     Treasury,          // 2 - Treasury management
     Governance,        // 3 - Governance changes
     LoyaltyChange,     // 4 - Loyalty application behavior changes
+    DaoConfigUpdate,   // 5 - DAO governance parameter changes
 }
 
 /// Configuration proposal status
@@ -39,6 +99,136 @@ pub enum ProposalStatus {
     Approved,   // Voting passed (can be executed)
     Rejected,   // Voting failed
     Executed,   // Effects executed on-chain
+    Cancelled,  // Withdrawn by the proposer before finalization
+    Vetoed,     // Blocked by the guardian during the timelock
+}
+
+/// Assets the DAO treasury can hold.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub enum TreasuryAssetKind {
+    Rac,        // 0 - $RAC governance token
+    Usdt,       // 1 - USDT stablecoin
+    WrappedSol, // 2 - Wrapped SOL
+}
+
+/// Lifecycle of a minted loyalty card.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub enum CardStatus {
+    Active,     // 0 - Held and usable by its owner
+    Upgraded,   // 1 - Burned in exchange for a higher tier card
+}
+
+/// How long a tier's earned rewards stay auto-staked.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum AutoStakingDuration {
+    Forever,    // 0 - Never unlocks
+    OneYear,    // 1 - 1 Year
+    TwoYears,   // 2 - 2 Years
+    FiveYears,  // 3 - 5 Years
+}
+
+impl AutoStakingDuration {
+    /// Lockup in seconds, or None for `Forever`.
+    pub fn lockup_seconds(&self) -> Option<i64> {
+        match self {
+            AutoStakingDuration::Forever => None,
+            AutoStakingDuration::OneYear => Some(SECONDS_PER_YEAR),
+            AutoStakingDuration::TwoYears => Some(2 * SECONDS_PER_YEAR),
+            AutoStakingDuration::FiveYears => Some(5 * SECONDS_PER_YEAR),
+        }
+    }
+
+    /// Parses the free-form values stored before the typed layout.
+    pub fn from_legacy(value: &str) -> Result<Self> {
+        match value.trim() {
+            "Forever" => Ok(AutoStakingDuration::Forever),
+            "1 Year" => Ok(AutoStakingDuration::OneYear),
+            "2 Years" => Ok(AutoStakingDuration::TwoYears),
+            "5 Years" => Ok(AutoStakingDuration::FiveYears),
+            _ => err!(CustomError::InvalidStakingDuration),
+        }
+    }
+}
+
+/// Scarcity tier of an NFT.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum Rarity {
+    Common,     // 0 - Common
+    LessCommon, // 1 - Less Common
+    Rare,       // 2 - Rare
+    VeryRare,   // 3 - Very Rare
+}
+
+impl Rarity {
+    /// Parses the free-form values stored before the typed layout.
+    pub fn from_legacy(value: &str) -> Result<Self> {
+        match value.trim() {
+            "Common" => Ok(Rarity::Common),
+            "Less Common" => Ok(Rarity::LessCommon),
+            "Rare" => Ok(Rarity::Rare),
+            "Very Rare" => Ok(Rarity::VeryRare),
+            _ => err!(CustomError::InvalidRarity),
+        }
+    }
+}
+
+/// Collection an NFT belongs to (the admin dropdown list).
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum Collection {
+    Classic,    // 0 - Classic
+    Premium,    // 1 - Premium
+    Elite,      // 2 - Elite
+    Exclusive,  // 3 - Exclusive
+}
+
+impl Collection {
+    /// Name used for the collection PDA seeds and the Metaplex collection metadata.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Collection::Classic => "Classic",
+            Collection::Premium => "Premium",
+            Collection::Elite => "Elite",
+            Collection::Exclusive => "Exclusive",
+        }
+    }
+
+    /// Parses the free-form values stored before the typed layout.
+    pub fn from_legacy(value: &str) -> Result<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "classic" => Ok(Collection::Classic),
+            "premium" => Ok(Collection::Premium),
+            "elite" => Ok(Collection::Elite),
+            "exclusive" => Ok(Collection::Exclusive),
+            _ => err!(CustomError::InvalidCollectionName),
+        }
+    }
+}
+
+/// Token allocation pools released through vesting schedules.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub enum VestingCategory {
+    Team,       // 0 - Team and advisors
+    Ecosystem,  // 1 - Ecosystem and partnerships
+    Rewards,    // 2 - Loyalty rewards pool
+}
+
+/// A voter's choice on a proposal. Abstentions count toward quorum only.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub enum VoteChoice {
+    Against,    // 0 - Vote against the proposal
+    For,        // 1 - Vote in favour of the proposal
+    Abstain,    // 2 - Counted for quorum, not for the outcome
+}
+
+impl VoteChoice {
+    pub fn from_u8(vote_type: u8) -> Result<Self> {
+        match vote_type {
+            0 => Ok(VoteChoice::Against),
+            1 => Ok(VoteChoice::For),
+            2 => Ok(VoteChoice::Abstain),
+            _ => err!(CustomError::InvalidVoteType),
+        }
+    }
 }
 
 /// The core program module for our DAO and NFT system.
@@ -47,30 +237,378 @@ pub mod solana_dao_nft_contract {
     use super::*;
 
     /// Initializes the DAO's governance token ($RAC) with a max supply,
-    /// and mints an initial amount. The mint authority is retained by the admin.
-    pub fn create_rac_mint(ctx: Context<CreateRacMint>) -> Result<()> {
+    /// and mints an initial amount. The mint authority is the DAO authority PDA,
+    /// so any further issuance has to go through `mint_rac`.
+    pub fn create_rac_mint(
+        ctx: Context<CreateRacMint>,
+        quorum_bps: u16,
+        voting_period_slots: u64,
+        min_proposal_threshold: u64,
+        execution_delay: i64,
+        grace_period: i64,
+        guardian: Pubkey,
+        usdt_mint: Pubkey,
+    ) -> Result<()> {
         let dao_config = &mut ctx.accounts.dao_config;
 
+        validate_governance_params(quorum_bps, voting_period_slots, execution_delay, grace_period)?;
+
+        dao_config.version = ACCOUNT_VERSION;
         dao_config.rac_mint = ctx.accounts.rac_mint.key();
-        dao_config.total_supply = 1_500_000_000; // The maximum total supply is 1.5 billion
+        dao_config.total_supply = RAC_MAX_SUPPLY; // The maximum total supply is 1.5 billion
+        dao_config.minted_supply = RAC_INITIAL_SUPPLY;
         dao_config.proposal_count = 0;
+        dao_config.quorum_bps = quorum_bps;
+        dao_config.voting_period_slots = voting_period_slots;
+        dao_config.min_proposal_threshold = min_proposal_threshold;
+        dao_config.admin_authority = ctx.accounts.admin.key();
+        dao_config.execution_delay = execution_delay;
+        dao_config.grace_period = grace_period;
+        dao_config.guardian = guardian;
+        dao_config.usdt_mint = usdt_mint;
 
-        let initial_mint_amount = 100_000_000; // The initial mint is 100 million
+        let seeds = &[b"dao_authority".as_ref(), &[ctx.bumps.dao_authority]];
+        let signer = &[&seeds[..]];
 
         // CPI to mint the initial 100 million tokens to the DAO's vault.
         let cpi_accounts = MintTo {
             mint: ctx.accounts.rac_mint.to_account_info(),
             to: ctx.accounts.dao_vault.to_account_info(),
-            authority: ctx.accounts.admin.to_account_info(),
+            authority: ctx.accounts.dao_authority.to_account_info(),
         };
         let cpi_program = ctx.accounts.token_program.to_account_info();
-        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-        token::mint_to(cpi_ctx, initial_mint_amount)?;
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::mint_to(cpi_ctx, RAC_INITIAL_SUPPLY)?;
+
+        let treasury_asset = &mut ctx.accounts.treasury_asset;
+        treasury_asset.version = ACCOUNT_VERSION;
+        treasury_asset.kind = TreasuryAssetKind::Rac;
+        treasury_asset.mint = ctx.accounts.rac_mint.key();
+        treasury_asset.vault = ctx.accounts.dao_vault.key();
+        treasury_asset.balance = RAC_INITIAL_SUPPLY;
+        treasury_asset.total_deposited = RAC_INITIAL_SUPPLY;
+        treasury_asset.total_withdrawn = 0;
+        treasury_asset.bump = ctx.bumps.treasury_asset;
+
+        emit!(TreasuryDeposit {
+            mint: treasury_asset.mint,
+            from: ctx.accounts.dao_authority.key(),
+            amount: RAC_INITIAL_SUPPLY,
+            balance: treasury_asset.balance,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
 
         msg!("$RAC token mint created. Initial supply: 100 million. Max supply: 1.5 billion.");
         Ok(())
     }
 
+    /// Sends lamports to the DAO authority PDA so governed instructions such as
+    /// `create_vesting_schedule` can pay rent for the accounts they create.
+    /// Anyone can fund it; the balance must stay above the rent-exempt minimum.
+    pub fn fund_dao_authority(ctx: Context<FundDaoAuthority>, lamports: u64) -> Result<()> {
+        require!(lamports > 0, CustomError::ZeroDepositAmount);
+
+        let cpi_accounts = system_program::Transfer {
+            from: ctx.accounts.funder.to_account_info(),
+            to: ctx.accounts.dao_authority.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
+        system_program::transfer(cpi_ctx, lamports)?;
+
+        msg!("DAO authority funded with {} lamports. Balance: {}", lamports, ctx.accounts.dao_authority.lamports());
+        Ok(())
+    }
+
+    /// Mints new $RAC (in base units) into the treasury, up to the 1.5 billion cap.
+    /// The DAO authority must sign, so this only runs as a stored instruction
+    /// of an approved Treasury proposal.
+    pub fn mint_rac(ctx: Context<MintRac>, amount: u64) -> Result<()> {
+        let dao_config = &mut ctx.accounts.dao_config;
+
+        require!(amount > 0, CustomError::ZeroMintAmount);
+        let new_minted_supply = checked_supply_add(dao_config.minted_supply, amount, dao_config.total_supply)?;
+
+        let seeds = &[b"dao_authority".as_ref(), &[ctx.bumps.dao_authority]];
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = MintTo {
+            mint: ctx.accounts.rac_mint.to_account_info(),
+            to: ctx.accounts.dao_vault.to_account_info(),
+            authority: ctx.accounts.dao_authority.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::mint_to(cpi_ctx, amount)?;
+
+        dao_config.minted_supply = new_minted_supply;
+
+        let treasury_asset = &mut ctx.accounts.treasury_asset;
+        treasury_asset.balance += amount;
+        treasury_asset.total_deposited += amount;
+
+        emit!(TreasuryDeposit {
+            mint: treasury_asset.mint,
+            from: ctx.accounts.dao_authority.key(),
+            amount,
+            balance: treasury_asset.balance,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Minted {} $RAC base units. Minted supply: {} of {}", amount, dao_config.minted_supply, dao_config.total_supply);
+        Ok(())
+    }
+
+    /// Creates a cliff + linear vesting schedule funded from the treasury's $RAC vault.
+    /// Total allocations across all schedules can never exceed the $RAC max supply.
+    /// Requires the DAO authority, so schedules are created through Treasury proposals.
+    pub fn create_vesting_schedule(
+        ctx: Context<CreateVestingSchedule>,
+        schedule_id: u64,
+        category: VestingCategory,
+        total_amount: u64,
+        start_ts: i64,
+        cliff_ts: i64,
+        end_ts: i64,
+    ) -> Result<()> {
+        let dao_config = &mut ctx.accounts.dao_config;
+
+        require!(total_amount > 0, CustomError::ZeroVestingAmount);
+        require!(start_ts <= cliff_ts && cliff_ts <= end_ts && start_ts < end_ts, CustomError::InvalidVestingSchedule);
+        require!(total_amount <= ctx.accounts.treasury_asset.balance, CustomError::InsufficientTreasuryBalance);
+        let new_allocated = checked_supply_add(dao_config.vesting_allocated, total_amount, dao_config.total_supply)?;
+
+        let seeds = &[b"dao_authority".as_ref(), &[ctx.bumps.dao_authority]];
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.dao_vault.to_account_info(),
+            to: ctx.accounts.vesting_vault.to_account_info(),
+            authority: ctx.accounts.dao_authority.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, total_amount)?;
+
+        dao_config.vesting_allocated = new_allocated;
+
+        let treasury_asset = &mut ctx.accounts.treasury_asset;
+        treasury_asset.balance -= total_amount;
+        treasury_asset.total_withdrawn += total_amount;
+
+        emit!(TreasuryWithdrawal {
+            mint: treasury_asset.mint,
+            to: ctx.accounts.vesting_vault.key(),
+            amount: total_amount,
+            balance: treasury_asset.balance,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        let vesting_schedule = &mut ctx.accounts.vesting_schedule;
+        vesting_schedule.version = ACCOUNT_VERSION;
+        vesting_schedule.beneficiary = ctx.accounts.beneficiary.key();
+        vesting_schedule.schedule_id = schedule_id;
+        vesting_schedule.category = category;
+        vesting_schedule.total_amount = total_amount;
+        vesting_schedule.claimed_amount = 0;
+        vesting_schedule.start_ts = start_ts;
+        vesting_schedule.cliff_ts = cliff_ts;
+        vesting_schedule.end_ts = end_ts;
+        vesting_schedule.revoked = false;
+        vesting_schedule.created_at = Clock::get()?.unix_timestamp;
+        vesting_schedule.bump = ctx.bumps.vesting_schedule;
+
+        msg!("Vesting schedule {} created for {}: {} $RAC base units", 
+             schedule_id, vesting_schedule.beneficiary, total_amount);
+        Ok(())
+    }
+
+    /// Releases the vested but unclaimed part of a schedule to the beneficiary.
+    pub fn claim_vested(ctx: Context<ClaimVested>) -> Result<()> {
+        let vesting_schedule = &ctx.accounts.vesting_schedule;
+        let current_timestamp = Clock::get()?.unix_timestamp;
+
+        let vested = vested_amount(
+            vesting_schedule.total_amount,
+            vesting_schedule.start_ts,
+            vesting_schedule.cliff_ts,
+            vesting_schedule.end_ts,
+            current_timestamp,
+        );
+        let claimable = vested.saturating_sub(vesting_schedule.claimed_amount);
+        require!(claimable > 0, CustomError::NothingToClaim);
+
+        let beneficiary_key = vesting_schedule.beneficiary;
+        let schedule_id_bytes = vesting_schedule.schedule_id.to_le_bytes();
+        let seeds = &[b"vesting".as_ref(), beneficiary_key.as_ref(), schedule_id_bytes.as_ref(), &[vesting_schedule.bump]];
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.vesting_vault.to_account_info(),
+            to: ctx.accounts.beneficiary_token_account.to_account_info(),
+            authority: ctx.accounts.vesting_schedule.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, claimable)?;
+
+        let vesting_schedule = &mut ctx.accounts.vesting_schedule;
+        vesting_schedule.claimed_amount += claimable;
+
+        msg!("Claimed {} vested $RAC base units. Total claimed: {}", claimable, vesting_schedule.claimed_amount);
+        Ok(())
+    }
+
+    /// Returns the unvested part of a schedule to the treasury. Already vested
+    /// tokens stay claimable. Requires the DAO authority, so it runs through a proposal.
+    pub fn revoke_vesting(ctx: Context<RevokeVesting>) -> Result<()> {
+        let vesting_schedule = &ctx.accounts.vesting_schedule;
+        let current_timestamp = Clock::get()?.unix_timestamp;
+
+        require!(!vesting_schedule.revoked, CustomError::VestingAlreadyRevoked);
+
+        let vested = vested_amount(
+            vesting_schedule.total_amount,
+            vesting_schedule.start_ts,
+            vesting_schedule.cliff_ts,
+            vesting_schedule.end_ts,
+            current_timestamp,
+        );
+        let unvested = vesting_schedule.total_amount - vested;
+
+        if unvested > 0 {
+            let beneficiary_key = vesting_schedule.beneficiary;
+            let schedule_id_bytes = vesting_schedule.schedule_id.to_le_bytes();
+            let seeds = &[b"vesting".as_ref(), beneficiary_key.as_ref(), schedule_id_bytes.as_ref(), &[vesting_schedule.bump]];
+            let signer = &[&seeds[..]];
+
+            let cpi_accounts = Transfer {
+                from: ctx.accounts.vesting_vault.to_account_info(),
+                to: ctx.accounts.dao_vault.to_account_info(),
+                authority: ctx.accounts.vesting_schedule.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+            token::transfer(cpi_ctx, unvested)?;
+
+            let treasury_asset = &mut ctx.accounts.treasury_asset;
+            treasury_asset.balance += unvested;
+            treasury_asset.total_deposited += unvested;
+
+            emit!(TreasuryDeposit {
+                mint: treasury_asset.mint,
+                from: ctx.accounts.vesting_vault.key(),
+                amount: unvested,
+                balance: treasury_asset.balance,
+                timestamp: current_timestamp,
+            });
+        }
+
+        let vesting_schedule = &mut ctx.accounts.vesting_schedule;
+        vesting_schedule.total_amount = vested;
+        vesting_schedule.end_ts = current_timestamp.min(vesting_schedule.end_ts);
+        vesting_schedule.cliff_ts = vesting_schedule.cliff_ts.min(vesting_schedule.end_ts);
+        vesting_schedule.revoked = true;
+
+        let dao_config = &mut ctx.accounts.dao_config;
+        dao_config.vesting_allocated = dao_config
+            .vesting_allocated
+            .checked_sub(unvested)
+            .ok_or(CustomError::InvalidVestingSchedule)?;
+
+        msg!("Vesting schedule {} revoked. {} unvested $RAC base units returned", vesting_schedule.schedule_id, unvested);
+        Ok(())
+    }
+
+    /// Opens a PDA-owned treasury vault for USDT or wrapped SOL.
+    /// The $RAC vault is created together with the mint in `create_rac_mint`.
+    pub fn initialize_treasury_asset(ctx: Context<InitializeTreasuryAsset>, kind: TreasuryAssetKind) -> Result<()> {
+        let dao_config = &ctx.accounts.dao_config;
+        let mint_key = ctx.accounts.mint.key();
+
+        let expected_mint = match kind {
+            TreasuryAssetKind::Rac => dao_config.rac_mint,
+            TreasuryAssetKind::Usdt => dao_config.usdt_mint,
+            TreasuryAssetKind::WrappedSol => spl_token::native_mint::ID,
+        };
+        require_keys_eq!(mint_key, expected_mint, CustomError::UnsupportedTreasuryAsset);
+
+        let treasury_asset = &mut ctx.accounts.treasury_asset;
+        treasury_asset.version = ACCOUNT_VERSION;
+        treasury_asset.kind = kind;
+        treasury_asset.mint = mint_key;
+        treasury_asset.vault = ctx.accounts.treasury_vault.key();
+        treasury_asset.balance = 0;
+        treasury_asset.total_deposited = 0;
+        treasury_asset.total_withdrawn = 0;
+        treasury_asset.bump = ctx.bumps.treasury_asset;
+
+        msg!("Treasury vault opened for mint {}", mint_key);
+        Ok(())
+    }
+
+    /// Deposits tokens into the treasury. Anyone can fund the DAO.
+    pub fn deposit_to_treasury(ctx: Context<DepositToTreasury>, amount: u64) -> Result<()> {
+        require!(amount > 0, CustomError::ZeroDepositAmount);
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.depositor_token_account.to_account_info(),
+            to: ctx.accounts.treasury_vault.to_account_info(),
+            authority: ctx.accounts.depositor.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        token::transfer(cpi_ctx, amount)?;
+
+        let treasury_asset = &mut ctx.accounts.treasury_asset;
+        treasury_asset.balance += amount;
+        treasury_asset.total_deposited += amount;
+
+        emit!(TreasuryDeposit {
+            mint: treasury_asset.mint,
+            from: ctx.accounts.depositor.key(),
+            amount,
+            balance: treasury_asset.balance,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Deposited {} to treasury. Balance: {}", amount, treasury_asset.balance);
+        Ok(())
+    }
+
+    /// Sends treasury funds to a recipient token account.
+    /// Requires the DAO authority, so it only runs through an approved Treasury proposal.
+    pub fn withdraw_from_treasury(ctx: Context<WithdrawFromTreasury>, amount: u64) -> Result<()> {
+        require!(amount > 0, CustomError::ZeroWithdrawalAmount);
+        require!(amount <= ctx.accounts.treasury_asset.balance, CustomError::InsufficientTreasuryBalance);
+
+        let seeds = &[b"dao_authority".as_ref(), &[ctx.bumps.dao_authority]];
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.treasury_vault.to_account_info(),
+            to: ctx.accounts.recipient_token_account.to_account_info(),
+            authority: ctx.accounts.dao_authority.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, amount)?;
+
+        let treasury_asset = &mut ctx.accounts.treasury_asset;
+        treasury_asset.balance -= amount;
+        treasury_asset.total_withdrawn += amount;
+
+        emit!(TreasuryWithdrawal {
+            mint: treasury_asset.mint,
+            to: ctx.accounts.recipient_token_account.key(),
+            amount,
+            balance: treasury_asset.balance,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Withdrew {} from treasury. Balance: {}", amount, treasury_asset.balance);
+        Ok(())
+    }
+
     /// Initializes the rewards configuration system.
     pub fn initialize_rewards_config(
         ctx: Context<InitializeRewardsConfig>,
@@ -80,14 +618,18 @@ pub mod solana_dao_nft_contract {
         let rewards_config = &mut ctx.accounts.rewards_config;
         let current_timestamp = Clock::get()?.unix_timestamp;
 
+        rewards_config.version = ACCOUNT_VERSION;
         rewards_config.program_id = ctx.accounts.program_id.key();
         rewards_config.admin_authority = ctx.accounts.admin.key();
         rewards_config.reward_token_mint = ctx.accounts.reward_token_mint.key();
         rewards_config.distribution_interval = distribution_interval;
         rewards_config.max_rewards_per_user = max_rewards_per_user;
+        rewards_config.reward_vault = ctx.accounts.reward_vault.key();
+        rewards_config.outstanding_income = 0;
         rewards_config.is_active = true;
         rewards_config.created_at = current_timestamp;
         rewards_config.updated_at = current_timestamp;
+        rewards_config.bump = ctx.bumps.rewards_config;
 
         msg!("Rewards configuration initialized with distribution interval: {} seconds", distribution_interval);
         Ok(())
@@ -96,19 +638,19 @@ pub mod solana_dao_nft_contract {
     /// Initializes a new NFT within the system with all the new fields.
     pub fn create_nft(
         ctx: Context<CreateNft>, 
-        collection_name: String,
+        collection: Collection,
         nft_name: String,
         display_name: String,
         symbol: String, 
         uri: String,
         is_custodial: bool,
         buy_price_usdt: u64,
-        rarity: String,
+        rarity: Rarity,
         mint_quantity: u64,
         is_upgradeable: bool,
         is_evolvable: bool,
         is_fractional_eligible: bool,
-        auto_staking_duration: String,
+        auto_staking_duration: AutoStakingDuration,
         earn_on_spend_ratio: u64,
         upgrade_bonus_ratio: u64,
         evolution_min_investment: u64,
@@ -117,75 +659,51 @@ pub mod solana_dao_nft_contract {
         custodial_income_rate: Option<u64>,
         fractional_supply: Option<u64>,
     ) -> Result<()> {
-        let nft_account = &mut ctx.accounts.nft;
+        let spec = CardTierSpec {
+            collection,
+            nft_name,
+            display_name,
+            symbol,
+            uri,
+            custody_type: if is_custodial { CustodyType::Custodial } else { CustodyType::NonCustodial },
+            buy_price_usdt,
+            rarity,
+            mint_quantity,
+            is_upgradeable,
+            is_evolvable,
+            is_fractional_eligible,
+            auto_staking_duration,
+            earn_on_spend_ratio,
+            upgrade_bonus_ratio,
+            evolution_min_investment,
+            evolution_earnings_ratio,
+            passive_income_rate,
+            custodial_income_rate,
+        };
         let current_timestamp = Clock::get()?.unix_timestamp;
-
-        // Basic Information
-        nft_account.admin = ctx.accounts.admin.key();
-        nft_account.collection_name = collection_name;
-        nft_account.nft_name = nft_name.clone();
-        nft_account.display_name = display_name;
-        nft_account.symbol = symbol.clone();
-        nft_account.uri = uri;
-        
-        // Pricing & Minting
-        nft_account.buy_price_usdt = buy_price_usdt;
-        nft_account.rarity = rarity;
-        nft_account.mint_quantity = mint_quantity;
-        
-        // Features & Capabilities
-        nft_account.is_upgradeable = is_upgradeable;
-        nft_account.is_evolvable = is_evolvable;
-        nft_account.is_fractional_eligible = is_fractional_eligible;
-        
-        // Auto Staking
-        nft_account.auto_staking_duration = auto_staking_duration;
-        
-        // Earning Ratios
-        nft_account.earn_on_spend_ratio = earn_on_spend_ratio;
-        nft_account.upgrade_bonus_ratio = upgrade_bonus_ratio;
-        
-        // Evolution Settings
-        nft_account.evolution_min_investment = evolution_min_investment;
-        nft_account.evolution_earnings_ratio = evolution_earnings_ratio;
-        
-        // Legacy fields for backward compatibility
-        nft_account.passive_income_rate = passive_income_rate;
-        nft_account.last_distribution_timestamp = current_timestamp;
-        
-        // Metadata
-        nft_account.is_active = true;
-        nft_account.created_at = current_timestamp;
-        nft_account.updated_at = current_timestamp;
+        ctx.accounts.nft.set_inner(spec.into_nft_account(ctx.accounts.admin.key(), current_timestamp)?);
 
         if is_custodial {
-            nft_account.custody_type = CustodyType::Custodial;
-            nft_account.custodial_income_rate = custodial_income_rate;
-            msg!("Custodial NFT created with name: {}", nft_account.nft_name);
+            msg!("Custodial NFT created with name: {}", ctx.accounts.nft.nft_name);
         } else {
             // FIX: Safely handle optional accounts for non-custodial NFTs
             let supply = fractional_supply.ok_or(CustomError::MissingFractionalSupply)?;
-            nft_account.custody_type = CustodyType::NonCustodial;
-            nft_account.custodial_income_rate = None;
+            let nft_key = ctx.accounts.nft.key();
 
             if let (Some(frac_nft_acc), Some(frac_mint_acc), Some(frac_vault_acc)) = 
                 (ctx.accounts.fractional_nft.as_mut(), ctx.accounts.fractional_mint.as_ref(), ctx.accounts.fractional_vault.as_ref()) {
                 
-                frac_nft_acc.parent_nft = nft_account.key();
-                frac_nft_acc.mint = frac_mint_acc.key();
-                frac_nft_acc.total_supply = supply;
-                frac_nft_acc.supply_cap = supply;
-                
-                let cpi_accounts = MintTo {
-                    mint: frac_mint_acc.to_account_info(),
-                    to: frac_vault_acc.to_account_info(),
-                    authority: ctx.accounts.admin.to_account_info(),
-                };
-                let cpi_program = ctx.accounts.token_program.to_account_info();
-                let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-                token::mint_to(cpi_ctx, supply)?;
+                mint_fractional_supply(
+                    frac_nft_acc,
+                    nft_key,
+                    frac_mint_acc,
+                    frac_vault_acc,
+                    &ctx.accounts.admin,
+                    &ctx.accounts.token_program,
+                    supply,
+                )?;
                 
-                msg!("Non-Custodial NFT created with name: {}", nft_account.nft_name);
+                msg!("Non-Custodial NFT created with name: {}", ctx.accounts.nft.nft_name);
             } else {
                 return err!(CustomError::MissingRequiredAccountsForNonCustodial);
             }
@@ -194,21 +712,96 @@ pub mod solana_dao_nft_contract {
         Ok(())
     }
 
-    /// Updates an existing NFT with new properties.
+    /// Creates the `NftAccount` of every card tier in `specs` in one instruction.
+    /// Pass each tier's NFT PDA, `[b"nft", &[custody_type as u8], nft_name, symbol]`, as a
+    /// writable remaining account in the same order as `specs`. Non-custodial tiers get their
+    /// fractional pool afterwards through `initialize_fractional_pool`.
+    pub fn initialize_catalog<'info>(
+        ctx: Context<'_, '_, '_, 'info, InitializeCatalog<'info>>,
+        specs: Vec<CardTierSpec>,
+    ) -> Result<()> {
+        require!(!specs.is_empty() && specs.len() <= MAX_CATALOG_SPECS, CustomError::InvalidCatalogSize);
+        require!(ctx.remaining_accounts.len() == specs.len(), CustomError::InvalidCatalogAccounts);
+
+        let admin_key = ctx.accounts.admin.key();
+        let current_timestamp = Clock::get()?.unix_timestamp;
+
+        for (spec, nft_info) in specs.into_iter().zip(ctx.remaining_accounts.iter()) {
+            let custody_seed = [spec.custody_type as u8];
+            let (nft_address, bump) = Pubkey::find_program_address(
+                &[b"nft", &custody_seed, spec.nft_name.as_bytes(), spec.symbol.as_bytes()],
+                &crate::ID,
+            );
+            require_keys_eq!(nft_address, nft_info.key(), CustomError::InvalidCatalogAccounts);
+            require!(nft_info.is_writable, CustomError::InvalidCatalogAccounts);
+
+            let nft_account = spec.into_nft_account(admin_key, current_timestamp)?;
+
+            let seeds = &[
+                b"nft".as_ref(),
+                &custody_seed,
+                nft_account.nft_name.as_bytes(),
+                nft_account.symbol.as_bytes(),
+                &[bump],
+            ];
+            let signer = &[&seeds[..]];
+
+            create_pda_account(
+                nft_info,
+                &ctx.accounts.admin,
+                &ctx.accounts.system_program,
+                NftAccount::LEN,
+                signer,
+            )?;
+
+            let mut data = nft_info.try_borrow_mut_data()?;
+            let mut writer: &mut [u8] = &mut data[..];
+            nft_account.try_serialize(&mut writer)?;
+
+            msg!("Catalog NFT created with name: {}", nft_account.nft_name);
+        }
+
+        Ok(())
+    }
+
+    /// Creates the fractional pool of a non-custodial NFT that was set up without one,
+    /// e.g. through `initialize_catalog`. The whole `fractional_supply` is minted into the vault.
+    pub fn initialize_fractional_pool(ctx: Context<InitializeFractionalPool>, fractional_supply: u64) -> Result<()> {
+        require!(
+            ctx.accounts.nft.custody_type == CustodyType::NonCustodial,
+            CustomError::InvalidCustodyType
+        );
+
+        let nft_key = ctx.accounts.nft.key();
+        mint_fractional_supply(
+            &mut ctx.accounts.fractional_nft,
+            nft_key,
+            &ctx.accounts.fractional_mint,
+            &ctx.accounts.fractional_vault,
+            &ctx.accounts.admin,
+            &ctx.accounts.token_program,
+            fractional_supply,
+        )?;
+
+        msg!("Fractional pool created for {} with supply {}", ctx.accounts.nft.nft_name, fractional_supply);
+        Ok(())
+    }
+
+    /// Updates an existing NFT with new properties. NFTs migrated from before the custody type
+    /// joined the seeds are still found at their original `[b"nft", nft_name, symbol]` address.
     pub fn update_nft(
         ctx: Context<UpdateNft>,
-        collection_name: String,
+        collection: Collection,
         nft_name: String,
         display_name: String,
         symbol: String,
         uri: String,
         buy_price_usdt: u64,
-        rarity: String,
-        mint_quantity: u64,
+        rarity: Rarity,
         is_upgradeable: bool,
         is_evolvable: bool,
         is_fractional_eligible: bool,
-        auto_staking_duration: String,
+        auto_staking_duration: AutoStakingDuration,
         earn_on_spend_ratio: u64,
         upgrade_bonus_ratio: u64,
         evolution_min_investment: u64,
@@ -216,18 +809,19 @@ pub mod solana_dao_nft_contract {
         passive_income_rate: u64,
         custodial_income_rate: Option<u64>,
     ) -> Result<()> {
+        validate_nft_fields(&nft_name, &display_name, &symbol, &uri)?;
+
         let nft_account = &mut ctx.accounts.nft;
         let current_timestamp = Clock::get()?.unix_timestamp;
 
         // Update all fields
-        nft_account.collection_name = collection_name;
+        nft_account.collection = collection;
         nft_account.nft_name = nft_name;
         nft_account.display_name = display_name;
         nft_account.symbol = symbol;
         nft_account.uri = uri;
         nft_account.buy_price_usdt = buy_price_usdt;
         nft_account.rarity = rarity;
-        nft_account.mint_quantity = mint_quantity;
         nft_account.is_upgradeable = is_upgradeable;
         nft_account.is_evolvable = is_evolvable;
         nft_account.is_fractional_eligible = is_fractional_eligible;
@@ -245,435 +839,4765 @@ pub mod solana_dao_nft_contract {
     }
 
     /// Allows a user to invest in a custodial NFT.
+    /// USDT is moved from the investor into the NFT's escrow and the amount actually
+    /// received is recorded. Each investment restarts the `auto_staking_duration` lockup.
     pub fn invest_in_custodial_nft(ctx: Context<InvestInCustodialNft>, investment_amount: u64) -> Result<()> {
+        require!(investment_amount > 0, CustomError::ZeroInvestmentAmount);
+        require!(ctx.accounts.nft.custody_type == CustodyType::Custodial, CustomError::InvalidCustodyType);
+
+        let current_timestamp = Clock::get()?.unix_timestamp;
+        let duration = ctx.accounts.nft.auto_staking_duration;
+        let balance_before = ctx.accounts.escrow_vault.amount;
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.investor_token_account.to_account_info(),
+            to: ctx.accounts.escrow_vault.to_account_info(),
+            authority: ctx.accounts.investor.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        token::transfer(cpi_ctx, investment_amount)?;
+
+        ctx.accounts.escrow_vault.reload()?;
+        let received = ctx.accounts.escrow_vault.amount - balance_before;
+        require!(received > 0, CustomError::ZeroInvestmentAmount);
+
         let investment_account = &mut ctx.accounts.investment_account;
         let nft = &mut ctx.accounts.nft;
-        
-        require!(investment_amount > 0, CustomError::ZeroInvestmentAmount);
-        require!(nft.custody_type == CustodyType::Custodial, CustomError::InvalidCustodyType);
-        
+
+        settle_income(
+            investment_account.amount,
+            nft.acc_income_per_share,
+            &mut investment_account.reward_debt,
+            &mut investment_account.pending_income,
+        );
+
+        investment_account.version = ACCOUNT_VERSION;
         investment_account.investor = ctx.accounts.investor.key();
         investment_account.nft = nft.key();
-        investment_account.amount += investment_amount;
+        investment_account.amount += received;
+        investment_account.reward_debt = income_debt(investment_account.amount, nft.acc_income_per_share);
+        investment_account.unlock_at = staking::unlock_time(duration, current_timestamp);
+        nft.total_shares += received;
 
-        msg!("Investor {} invested {} in custodial NFT.", ctx.accounts.investor.key(), investment_amount);
+        msg!("Investor {} invested {} in custodial NFT.", ctx.accounts.investor.key(), received);
         Ok(())
     }
 
-    /// Allows a user to invest in a non-custodial NFT.
-    pub fn invest_in_non_custodial_nft(ctx: Context<InvestInNonCustodialNft>, investment_amount: u64) -> Result<()> {
+    /// Returns escrowed USDT to a custodial investor once their lockup has passed.
+    /// NFTs with a "Forever" auto-staking duration can never be withdrawn from.
+    pub fn withdraw_custodial_investment(ctx: Context<WithdrawCustodialInvestment>, amount: u64) -> Result<()> {
         let investment_account = &mut ctx.accounts.investment_account;
         let nft = &mut ctx.accounts.nft;
-        let fractional_nft = &mut ctx.accounts.fractional_nft;
+        let current_timestamp = Clock::get()?.unix_timestamp;
 
-        require!(investment_amount > 0, CustomError::ZeroInvestmentAmount);
-        require!(nft.custody_type == CustodyType::NonCustodial, CustomError::InvalidCustodyType);
-        require!(fractional_nft.total_supply + investment_amount <= fractional_nft.supply_cap, CustomError::SupplyCapReached);
-        
-        investment_account.investor = ctx.accounts.investor.key();
-        investment_account.nft = nft.key();
-        investment_account.amount += investment_amount;
-        
-        fractional_nft.total_supply += investment_amount;
-        
-        let cpi_accounts = MintTo {
-            mint: ctx.accounts.fractional_mint.to_account_info(),
+        require!(amount > 0, CustomError::ZeroWithdrawalAmount);
+        require!(amount <= investment_account.amount, CustomError::InsufficientInvestment);
+        require!(current_timestamp >= investment_account.unlock_at, CustomError::InvestmentLocked);
+
+        settle_income(
+            investment_account.amount,
+            nft.acc_income_per_share,
+            &mut investment_account.reward_debt,
+            &mut investment_account.pending_income,
+        );
+
+        investment_account.amount -= amount;
+        investment_account.reward_debt = income_debt(investment_account.amount, nft.acc_income_per_share);
+        nft.total_shares -= amount;
+
+        let nft_key = nft.key();
+        let seeds = &[b"escrow_authority".as_ref(), nft_key.as_ref(), &[ctx.bumps.escrow_authority]];
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.escrow_vault.to_account_info(),
             to: ctx.accounts.investor_token_account.to_account_info(),
-            authority: ctx.accounts.admin.to_account_info(),
+            authority: ctx.accounts.escrow_authority.to_account_info(),
         };
         let cpi_program = ctx.accounts.token_program.to_account_info();
-        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-        token::mint_to(cpi_ctx, investment_amount)?;
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, amount)?;
 
-        msg!("Investor {} invested {} in non-custodial NFT.", ctx.accounts.investor.key(), investment_amount);
+        msg!("Investor {} withdrew {} from custodial NFT.", ctx.accounts.investor.key(), amount);
         Ok(())
     }
-    
-    /// This is a placeholder for the passive income distribution logic.
-    pub fn distribute_passive_income(ctx: Context<DistributeIncome>) -> Result<()> {
-        let nft_account = &mut ctx.accounts.nft;
-        let current_timestamp = Clock::get()?.unix_timestamp;
-        
-        if nft_account.custody_type == CustodyType::Custodial {
-            // Distribute based on the higher custodial_income_rate to investors
-        } else {
-            // Distribute based on the lower passive_income_rate to token holders
-        }
 
-        nft_account.last_distribution_timestamp = current_timestamp;
-        msg!("Passive income distribution placeholder executed.");
-        Ok(())
-    }
-    
-    /// Allows the DAO admin to update the passive income rate of an NFT.
-    pub fn update_passive_income_rate(ctx: Context<UpdateRate>, new_rate: u64) -> Result<()> {
-        let nft_account = &mut ctx.accounts.nft;
-        nft_account.passive_income_rate = new_rate;
+    /// Buys fractional shares of a non-custodial NFT.
+    /// The buyer pays `shares * buy_price_usdt / supply` USDT into the treasury and the shares
+    /// move from the NFT's fractional vault into the buyer's share escrow, where they earn income.
+    /// `max_cost` guards against price changes.
+    pub fn buy_fractional_shares(ctx: Context<BuyFractionalShares>, shares: u64, max_cost: u64) -> Result<()> {
+        let nft = &ctx.accounts.nft;
+        let fractional_nft = &ctx.accounts.fractional_nft;
 
-        msg!("Passive income rate for NFT updated to {}", new_rate);
-        Ok(())
-    }
+        require!(shares > 0, CustomError::ZeroInvestmentAmount);
+        require!(nft.custody_type == CustodyType::NonCustodial, CustomError::InvalidCustodyType);
+        require!(nft.is_active, CustomError::NftNotActive);
+        require!(fractional_nft.sold + shares <= fractional_nft.supply_cap, CustomError::SupplyCapReached);
 
-    
-    /// Allows a user to create a new proposal.
-    pub fn create_proposal(ctx: Context<CreateProposal>, dao_org: u8, proposal_type: u8, description: String) -> Result<()> {
-        let dao_config = &mut ctx.accounts.dao_config;
-        let proposal_account = &mut ctx.accounts.proposal;
+        let cost = fractional_share_cost(shares, nft.buy_price_usdt, fractional_nft.supply_cap)?;
+        require!(cost <= max_cost, CustomError::PriceExceedsMaximum);
 
-        dao_config.proposal_count += 1;
-        proposal_account.proposal_id = dao_config.proposal_count;
-        proposal_account.proposer = ctx.accounts.proposer.key();
-        proposal_account.proposal_type = proposal_type;
-        proposal_account.description = description;
-        proposal_account.votes_for = 0;
-        proposal_account.votes_against = 0;
-        proposal_account.dao_org = dao_org;
-        proposal_account.status = ProposalStatus::Draft as u8;
-        proposal_account.created_at = Clock::get()?.unix_timestamp;
-        proposal_account.end_slot = Clock::get()?.slot + dao_config.voting_period_slots;
-        proposal_account.executed_at = None;
-        
-        msg!("New proposal created: {}", proposal_account.description);
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.buyer_usdt_account.to_account_info(),
+            to: ctx.accounts.treasury_vault.to_account_info(),
+            authority: ctx.accounts.investor.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        token::transfer(cpi_ctx, cost)?;
+
+        let nft_key = nft.key();
+        let seeds = &[b"fractional_authority".as_ref(), nft_key.as_ref(), &[ctx.bumps.fractional_authority]];
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.fractional_vault.to_account_info(),
+            to: ctx.accounts.share_escrow.to_account_info(),
+            authority: ctx.accounts.fractional_authority.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, shares)?;
+
+        let investment_account = &mut ctx.accounts.investment_account;
+        let nft = &mut ctx.accounts.nft;
+
+        settle_income(
+            investment_account.amount,
+            nft.acc_income_per_share,
+            &mut investment_account.reward_debt,
+            &mut investment_account.pending_income,
+        );
+
+        investment_account.version = ACCOUNT_VERSION;
+        investment_account.investor = ctx.accounts.investor.key();
+        investment_account.nft = nft.key();
+        investment_account.amount += shares;
+        investment_account.reward_debt = income_debt(investment_account.amount, nft.acc_income_per_share);
+        nft.total_shares += shares;
+
+        ctx.accounts.fractional_nft.sold += shares;
+
+        let treasury_asset = &mut ctx.accounts.treasury_asset;
+        treasury_asset.balance += cost;
+        treasury_asset.total_deposited += cost;
+
+        emit!(TreasuryDeposit {
+            mint: treasury_asset.mint,
+            from: ctx.accounts.investor.key(),
+            amount: cost,
+            balance: treasury_asset.balance,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Investor {} bought {} shares of {} for {} USDT.", ctx.accounts.investor.key(), shares, nft.nft_name, cost);
         Ok(())
     }
 
-    /// Allows a user to create a rewards configuration proposal.
-    pub fn create_config_proposal(
-        ctx: Context<CreateConfigProposal>,
-        dao_org: u8,
-        proposed_distribution_interval: u64,
-        proposed_max_rewards_per_user: u64,
-        description: String
-    ) -> Result<()> {
-        let dao_config = &mut ctx.accounts.dao_config;
-        let proposal_account = &mut ctx.accounts.proposal;
-        let config_proposal = &mut ctx.accounts.config_proposal;
+    /// Moves fractional shares from the investor's wallet into their share escrow.
+    /// Only escrowed shares earn income, so shares bought from another holder start
+    /// earning once deposited here.
+    pub fn deposit_fractional_shares(ctx: Context<DepositFractionalShares>, shares: u64) -> Result<()> {
+        require!(shares > 0, CustomError::ZeroInvestmentAmount);
+        require!(ctx.accounts.nft.custody_type == CustodyType::NonCustodial, CustomError::InvalidCustodyType);
 
-        dao_config.proposal_count += 1;
-        proposal_account.proposal_id = dao_config.proposal_count;
-        proposal_account.proposer = ctx.accounts.proposer.key();
-        proposal_account.proposal_type = ProposalType::ConfigUpdate as u8;
-        proposal_account.description = description;
-        proposal_account.votes_for = 0;
-        proposal_account.votes_against = 0;
-        proposal_account.dao_org = dao_org;
-        proposal_account.status = ProposalStatus::Draft as u8;
-        proposal_account.created_at = Clock::get()?.unix_timestamp;
-        proposal_account.end_slot = Clock::get()?.slot + dao_config.voting_period_slots;
-        proposal_account.executed_at = None;
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.investor_token_account.to_account_info(),
+            to: ctx.accounts.share_escrow.to_account_info(),
+            authority: ctx.accounts.investor.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        token::transfer(cpi_ctx, shares)?;
 
-        config_proposal.proposal_id = dao_config.proposal_count;
-        config_proposal.proposed_distribution_interval = proposed_distribution_interval;
-        config_proposal.proposed_max_rewards_per_user = proposed_max_rewards_per_user;
-        config_proposal.status = ConfigProposalStatus::Pending;
-        config_proposal.created_at = Clock::get()?.unix_timestamp;
-        
-        msg!("New configuration proposal created: Distribution interval: {}, Max rewards: {}", 
-             proposed_distribution_interval, proposed_max_rewards_per_user);
+        let investment_account = &mut ctx.accounts.investment_account;
+        let nft = &mut ctx.accounts.nft;
+
+        settle_income(
+            investment_account.amount,
+            nft.acc_income_per_share,
+            &mut investment_account.reward_debt,
+            &mut investment_account.pending_income,
+        );
+
+        investment_account.version = ACCOUNT_VERSION;
+        investment_account.investor = ctx.accounts.investor.key();
+        investment_account.nft = nft.key();
+        investment_account.amount += shares;
+        investment_account.reward_debt = income_debt(investment_account.amount, nft.acc_income_per_share);
+        nft.total_shares += shares;
+
+        msg!("Investor {} deposited {} shares of {}", ctx.accounts.investor.key(), shares, nft.nft_name);
         Ok(())
     }
 
-    /// CRITICAL: Create a DAO proposal for loyalty behavior changes
-    /// This function enforces the rule requirement for loyalty changes
-    pub fn create_loyalty_change_proposal(
-        ctx: Context<CreateLoyaltyChangeProposal>,
-        dao_org: u8,
-        change_type: u8,
-        parameter_name: String,
-        old_value: String,
-        new_value: String,
-        reason: String,
-    ) -> Result<()> {
-        let dao_config = &mut ctx.accounts.dao_config;
-        let proposal_account = &mut ctx.accounts.proposal;
-        let loyalty_proposal = &mut ctx.accounts.loyalty_proposal;
+    /// Moves fractional shares out of the investor's share escrow into their wallet,
+    /// where they can be traded. Income accrued so far stays claimable.
+    pub fn withdraw_fractional_shares(ctx: Context<WithdrawFractionalShares>, shares: u64) -> Result<()> {
+        let investment_account = &mut ctx.accounts.investment_account;
+        let nft = &mut ctx.accounts.nft;
 
-        // Validate change type
-        require!(change_type < 12, CustomError::InvalidChangeType);
+        require!(shares > 0, CustomError::ZeroWithdrawalAmount);
+        require!(shares <= investment_account.amount, CustomError::InsufficientInvestment);
 
-        dao_config.proposal_count += 1;
-        proposal_account.proposal_id = dao_config.proposal_count;
-        proposal_account.proposer = ctx.accounts.proposer.key();
-        proposal_account.proposal_type = ProposalType::LoyaltyChange as u8;
-        proposal_account.description = format!(
-            "Loyalty Change: {} - {}: {} -> {}",
-            get_change_type_name(change_type),
-            parameter_name,
-            old_value,
-            new_value
+        settle_income(
+            investment_account.amount,
+            nft.acc_income_per_share,
+            &mut investment_account.reward_debt,
+            &mut investment_account.pending_income,
         );
-        proposal_account.votes_for = 0;
-        proposal_account.votes_against = 0;
-        proposal_account.dao_org = dao_org;
-        proposal_account.status = ProposalStatus::Draft as u8;
-        proposal_account.created_at = Clock::get()?.unix_timestamp;
-        proposal_account.end_slot = Clock::get()?.slot + dao_config.voting_period_slots;
-        proposal_account.executed_at = None;
 
-        loyalty_proposal.proposal_id = dao_config.proposal_count;
-        loyalty_proposal.change_type = change_type;
-        loyalty_proposal.parameter_name = parameter_name;
-        loyalty_proposal.old_value = old_value;
-        loyalty_proposal.new_value = new_value;
-        loyalty_proposal.reason = reason;
-        loyalty_proposal.status = ConfigProposalStatus::Pending;
-        loyalty_proposal.created_at = Clock::get()?.unix_timestamp;
-        
-        msg!("New loyalty change proposal created: {} - {}: {} -> {}", 
-             get_change_type_name(change_type), parameter_name, old_value, new_value);
+        investment_account.amount -= shares;
+        investment_account.reward_debt = income_debt(investment_account.amount, nft.acc_income_per_share);
+        nft.total_shares -= shares;
+
+        let nft_key = nft.key();
+        let seeds = &[b"fractional_authority".as_ref(), nft_key.as_ref(), &[ctx.bumps.fractional_authority]];
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.share_escrow.to_account_info(),
+            to: ctx.accounts.investor_token_account.to_account_info(),
+            authority: ctx.accounts.fractional_authority.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, shares)?;
+
+        msg!("Investor {} withdrew {} shares of {}", ctx.accounts.investor.key(), shares, ctx.accounts.nft.nft_name);
         Ok(())
     }
     
-    /// Allows a user to vote on a proposal using their token balance as weight.
-    pub fn vote(ctx: Context<Vote>, proposal_id: u64, vote_type: u8) -> Result<()> {
-        let proposal_account = &mut ctx.accounts.proposal;
-        let voter_record = &mut ctx.accounts.voter_record;
+    /// Accrues passive income for an NFT since the last distribution into its
+    /// reward-per-share accumulator. Custodial NFTs accrue at `custodial_income_rate`,
+    /// non-custodial NFTs at `passive_income_rate` (annual basis points).
+    /// Income is computed on the USDT value of the shares and paid in reward token base units.
+    /// Anyone can crank this once per `distribution_interval`; holders claim lazily.
+    pub fn distribute_passive_income(ctx: Context<DistributeIncome>) -> Result<()> {
+        let nft_account = &mut ctx.accounts.nft;
+        let rewards_config = &mut ctx.accounts.rewards_config;
+        let current_timestamp = Clock::get()?.unix_timestamp;
 
-        // FIX: Implement token-weighted voting
-        let voter_token_balance = ctx.accounts.rac_token_account.amount;
-        require!(voter_token_balance > 0, CustomError::InsufficientBalanceForVote);
+        require!(rewards_config.is_active, CustomError::RewardsInactive);
+        let elapsed = current_timestamp - nft_account.last_distribution_timestamp;
+        require!(elapsed >= rewards_config.distribution_interval as i64, CustomError::DistributionTooEarly);
 
-        voter_record.voter = ctx.accounts.voter.key();
-        voter_record.proposal_id = proposal_id;
-        
-        if vote_type == 1 {
-            proposal_account.votes_for += voter_token_balance;
-            msg!("Voted FOR proposal {} with a weight of {}", proposal_id, voter_token_balance);
-        } else if vote_type == 0 {
-            proposal_account.votes_against += voter_token_balance;
-            msg!("Voted AGAINST proposal {} with a weight of {}", proposal_id, voter_token_balance);
+        let rate = if nft_account.custody_type == CustodyType::Custodial {
+            // Distribute based on the higher custodial_income_rate to investors
+            nft_account.custodial_income_rate.unwrap_or(nft_account.passive_income_rate)
+        } else {
+            // Distribute based on the lower passive_income_rate to token holders
+            nft_account.passive_income_rate
+        };
+
+        // Custodial shares are escrowed USDT; fractional shares are valued at the NFT's price per share
+        let principal_usdt = if nft_account.custody_type == CustodyType::Custodial {
+            nft_account.total_shares as u128
+        } else {
+            let fractional_nft = ctx.accounts.fractional_nft.as_ref().ok_or(CustomError::InvalidFractionalAccount)?;
+            require!(fractional_nft.supply_cap > 0, CustomError::InvalidFractionalAccount);
+            (nft_account.total_shares as u128) * (nft_account.buy_price_usdt as u128) / (fractional_nft.supply_cap as u128)
+        };
+        let income_usdt = principal_usdt * (rate as u128) * (elapsed as u128) / (10_000 * SECONDS_PER_YEAR as u128);
+        let income = usdt_to_reward_units(income_usdt, ctx.accounts.usdt_mint.decimals, ctx.accounts.reward_mint.decimals)?;
+
+        if income > 0 {
+            let committed = rewards_config.outstanding_income
+                .checked_add(income)
+                .ok_or(CustomError::InsufficientRewardFunds)?;
+            require!(ctx.accounts.reward_vault.amount >= committed, CustomError::InsufficientRewardFunds);
+
+            nft_account.acc_income_per_share += (income as u128) * INCOME_PRECISION / (nft_account.total_shares as u128);
+            rewards_config.outstanding_income = committed;
         }
 
+        nft_account.last_distribution_timestamp = current_timestamp;
+        msg!("Distributed {} passive income across {} shares of {}", income, nft_account.total_shares, nft_account.nft_name);
         Ok(())
     }
 
-    /// Allows DAO to execute approved configuration proposals.
-    pub fn execute_config_proposal(ctx: Context<ExecuteConfigProposal>, proposal_id: u64) -> Result<()> {
-        let proposal_account = &ctx.accounts.proposal;
-        let config_proposal = &mut ctx.accounts.config_proposal;
+    /// Pays a fractional holder the income accrued on their non-custodial NFT investment.
+    /// Claims are limited to one per `distribution_interval` and to `max_rewards_per_user`
+    /// per claim; anything above the cap stays pending for the next claim.
+    /// Custodial income is auto-staked instead, through `claim_staking_rewards`.
+    pub fn claim_nft_income(ctx: Context<ClaimNftIncome>) -> Result<()> {
+        let nft_account = &ctx.accounts.nft;
+        let rewards_config = &ctx.accounts.rewards_config;
+        let investment = &mut ctx.accounts.investment;
+        let current_timestamp = Clock::get()?.unix_timestamp;
+
+        require!(nft_account.custody_type == CustodyType::NonCustodial, CustomError::AutoStakingRequired);
+
+        let payout = take_claimable_income(
+            investment.amount,
+            nft_account.acc_income_per_share,
+            &mut investment.reward_debt,
+            &mut investment.pending_income,
+            &mut investment.last_claim_at,
+            rewards_config,
+            current_timestamp,
+        )?;
+
+        let seeds = &[b"rewards_config".as_ref(), &[rewards_config.bump]];
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.reward_vault.to_account_info(),
+            to: ctx.accounts.investor_reward_account.to_account_info(),
+            authority: ctx.accounts.rewards_config.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, payout)?;
+
         let rewards_config = &mut ctx.accounts.rewards_config;
+        rewards_config.outstanding_income -= payout;
 
-        require!(proposal_account.proposal_id == proposal_id, CustomError::InvalidProposalId);
-        require!(proposal_account.proposal_type == ProposalType::ConfigUpdate as u8, CustomError::InvalidProposalType);
-        require!(proposal_account.status == ProposalStatus::Approved as u8, CustomError::ProposalNotApproved);
+        msg!("Investor {} claimed {} passive income", ctx.accounts.investor.key(), payout);
+        Ok(())
+    }
 
-        // Check if proposal has enough votes to pass (simple majority)
-        let total_votes = proposal_account.votes_for + proposal_account.votes_against;
-        require!(total_votes > 0, CustomError::NoVotesCast);
-        require!(proposal_account.votes_for > proposal_account.votes_against, CustomError::ProposalNotPassed);
+    /// Claims a custodial investor's accrued income straight into their reward stake,
+    /// locked for the NFT's `auto_staking_duration`. Custodial income is paid to card holders
+    /// of the NFT only: pass `loyalty_card`, or `card_proof` with the card tree accounts and
+    /// the proof nodes as remaining accounts for a compressed card.
+    pub fn claim_staking_rewards<'info>(
+        ctx: Context<'_, '_, '_, 'info, ClaimStakingRewards<'info>>,
+        card_proof: Option<CompressedCardProof>,
+    ) -> Result<()> {
+        let merkle_tree = ctx.accounts.merkle_tree.as_ref().map(|tree| tree.to_account_info());
+        let compression_program = ctx.accounts.compression_program.as_ref().map(|program| program.to_account_info());
+        let (card_nft, _) = verify_card_holder(
+            ctx.accounts.investor.key(),
+            ctx.accounts.loyalty_card.as_deref(),
+            ctx.accounts.card_tree.as_deref(),
+            merkle_tree.as_ref(),
+            compression_program.as_ref(),
+            card_proof.as_ref(),
+            ctx.remaining_accounts,
+        )?;
+        require!(card_nft == ctx.accounts.nft.key(), CustomError::CardHolderRequired);
 
-        // Execute the configuration changes
-        rewards_config.distribution_interval = config_proposal.proposed_distribution_interval;
-        rewards_config.max_rewards_per_user = config_proposal.proposed_max_rewards_per_user;
-        rewards_config.updated_at = Clock::get()?.unix_timestamp;
+        let nft_account = &ctx.accounts.nft;
+        let rewards_config = &ctx.accounts.rewards_config;
+        let investment = &mut ctx.accounts.investment;
+        let current_timestamp = Clock::get()?.unix_timestamp;
 
-        // Mark proposal as implemented
-        config_proposal.status = ConfigProposalStatus::Implemented;
-        config_proposal.implemented_at = Some(Clock::get()?.unix_timestamp);
+        require!(nft_account.custody_type == CustodyType::Custodial, CustomError::InvalidCustodyType);
 
-        msg!("Configuration proposal {} executed successfully", proposal_id);
+        let payout = take_claimable_income(
+            investment.amount,
+            nft_account.acc_income_per_share,
+            &mut investment.reward_debt,
+            &mut investment.pending_income,
+            &mut investment.last_claim_at,
+            rewards_config,
+            current_timestamp,
+        )?;
+
+        let seeds = &[b"rewards_config".as_ref(), &[rewards_config.bump]];
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.reward_vault.to_account_info(),
+            to: ctx.accounts.stake_vault.to_account_info(),
+            authority: ctx.accounts.rewards_config.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, payout)?;
+
+        ctx.accounts.rewards_config.outstanding_income -= payout;
+
+        let reward_stake = &mut ctx.accounts.reward_stake;
+        reward_stake.version = ACCOUNT_VERSION;
+        reward_stake.owner = ctx.accounts.investor.key();
+        reward_stake.nft = nft_account.key();
+        reward_stake.bump = ctx.bumps.reward_stake;
+        staking::add_stake(reward_stake, payout, nft_account.auto_staking_duration, current_timestamp);
+
+        msg!("Investor {} auto-staked {} income until {}", reward_stake.owner, payout, reward_stake.unlock_at);
         Ok(())
     }
-}
 
-// Helper function to get change type name
-fn get_change_type_name(change_type: u8) -> &'static str {
-    match change_type {
-        0 => "Point Release Delay",
-        1 => "Referral Parameters",
-        2 => "NFT Earning Ratios",
-        3 => "Loyalty Network Settings",
-        4 => "Merchant Limits",
-        5 => "Inactivity Timeout",
-        6 => "SMS OTP Settings",
-        7 => "Subscription Plans",
-        8 => "Asset Initiative Selection",
-        9 => "Wallet Management",
-        10 => "Payment Gateway",
-        11 => "Email Notifications",
-        _ => "Unknown Change Type",
+    /// Stakes reward tokens against an NFT tier, locked for its `auto_staking_duration`.
+    /// Every new stake pushes the position's unlock time out to a full duration from now.
+    pub fn stake_rewards(ctx: Context<StakeRewards>, amount: u64) -> Result<()> {
+        require!(amount > 0, CustomError::ZeroDepositAmount);
+        let current_timestamp = Clock::get()?.unix_timestamp;
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.owner_reward_account.to_account_info(),
+            to: ctx.accounts.stake_vault.to_account_info(),
+            authority: ctx.accounts.owner.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        token::transfer(cpi_ctx, amount)?;
+
+        let reward_stake = &mut ctx.accounts.reward_stake;
+        reward_stake.version = ACCOUNT_VERSION;
+        reward_stake.owner = ctx.accounts.owner.key();
+        reward_stake.nft = ctx.accounts.nft.key();
+        reward_stake.bump = ctx.bumps.reward_stake;
+        staking::add_stake(reward_stake, amount, ctx.accounts.nft.auto_staking_duration, current_timestamp);
+
+        msg!("{} staked {} rewards until {}", reward_stake.owner, amount, reward_stake.unlock_at);
+        Ok(())
+    }
+
+    /// Returns staked rewards once the position's lock has expired.
+    pub fn unstake_rewards(ctx: Context<UnstakeRewards>, amount: u64) -> Result<()> {
+        let current_timestamp = Clock::get()?.unix_timestamp;
+        staking::check_unstake(&ctx.accounts.reward_stake, amount, current_timestamp)?;
+
+        let nft_key = ctx.accounts.nft.key();
+        let owner_key = ctx.accounts.owner.key();
+        let seeds = &[b"reward_stake".as_ref(), nft_key.as_ref(), owner_key.as_ref(), &[ctx.accounts.reward_stake.bump]];
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.stake_vault.to_account_info(),
+            to: ctx.accounts.owner_reward_account.to_account_info(),
+            authority: ctx.accounts.reward_stake.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, amount)?;
+
+        let reward_stake = &mut ctx.accounts.reward_stake;
+        reward_stake.amount -= amount;
+
+        msg!("{} unstaked {} rewards. Remaining: {}", owner_key, amount, reward_stake.amount);
+        Ok(())
+    }
+
+    /// Converts an `NftAccount` written before the `version` byte to the current layout,
+    /// growing it to `NftAccount::LEN`. Only the NFT's admin can migrate it.
+    pub fn migrate_nft_account(ctx: Context<MigrateNftAccount>) -> Result<()> {
+        let nft_info = ctx.accounts.nft.to_account_info();
+        let migrated = migration::upgrade_nft_account(&nft_info.try_borrow_data()?)?;
+        require_keys_eq!(migrated.admin, ctx.accounts.admin.key(), CustomError::Unauthorized);
+
+        write_migrated_account(&nft_info, &ctx.accounts.admin, &ctx.accounts.system_program, NftAccount::LEN, &migrated)?;
+
+        msg!("Migrated NFT account {} to version {}", migrated.nft_name, ACCOUNT_VERSION);
+        Ok(())
+    }
+
+    /// Converts the `DaoConfig` written before the `version` byte to the current layout.
+    /// `args` fills in the parameters older layouts did not store. Only the program's
+    /// upgrade authority can migrate it.
+    pub fn migrate_dao_config(ctx: Context<MigrateDaoConfig>, args: migration::DaoConfigMigrationArgs) -> Result<()> {
+        let dao_config_info = ctx.accounts.dao_config.to_account_info();
+        let migrated = migration::upgrade_dao_config(
+            &dao_config_info.try_borrow_data()?,
+            &args,
+            ctx.accounts.rac_mint.supply,
+        )?;
+        require_keys_eq!(migrated.rac_mint, ctx.accounts.rac_mint.key(), CustomError::InvalidRacMint);
+        validate_governance_params(
+            migrated.quorum_bps,
+            migrated.voting_period_slots,
+            migrated.execution_delay,
+            migrated.grace_period,
+        )?;
+
+        write_migrated_account(&dao_config_info, &ctx.accounts.authority, &ctx.accounts.system_program, DaoConfig::LEN, &migrated)?;
+
+        msg!("Migrated DAO config to version {}", ACCOUNT_VERSION);
+        Ok(())
+    }
+
+    /// Converts a `Proposal` written before the `version` byte to the current layout.
+    /// The conversion only depends on the stored data, so anyone can pay for it.
+    pub fn migrate_proposal(ctx: Context<MigrateProposal>) -> Result<()> {
+        let proposal_info = ctx.accounts.proposal.to_account_info();
+        let migrated = migration::upgrade_proposal(&proposal_info.try_borrow_data()?)?;
+
+        write_migrated_account(&proposal_info, &ctx.accounts.payer, &ctx.accounts.system_program, Proposal::LEN, &migrated)?;
+
+        msg!("Migrated proposal {} to version {}", migrated.proposal_id, ACCOUNT_VERSION);
+        Ok(())
+    }
+
+    /// Converts any other fixed-size account written before the `version` byte existed.
+    /// Accounts that kept their shape just move one byte along; the original investment,
+    /// fractional pool and voter record layouts are converted by `migration::upgrade_fixed_account`.
+    pub fn migrate_account_version(ctx: Context<MigrateAccountVersion>) -> Result<()> {
+        let account_info = ctx.accounts.account.to_account_info();
+        let migrated = migration::upgrade_fixed_account(&account_info.try_borrow_data()?)?;
+
+        grow_account(&account_info, &ctx.accounts.payer, &ctx.accounts.system_program, migrated.len())?;
+        account_info.try_borrow_mut_data()?.copy_from_slice(&migrated);
+
+        msg!("Migrated account {} to version {}", account_info.key(), ACCOUNT_VERSION);
+        Ok(())
+    }
+
+    /// Converts the original `RewardsConfig` to the current layout and creates the reward vault
+    /// it did not have. Only the stored admin authority can migrate it.
+    pub fn migrate_rewards_config(ctx: Context<MigrateRewardsConfig>) -> Result<()> {
+        let rewards_config_info = ctx.accounts.rewards_config.to_account_info();
+        let migrated = migration::upgrade_rewards_config(
+            &rewards_config_info.try_borrow_data()?,
+            ctx.accounts.reward_vault.key(),
+            ctx.bumps.rewards_config,
+        )?;
+        require_keys_eq!(migrated.admin_authority, ctx.accounts.admin.key(), CustomError::UnauthorizedAdmin);
+        require_keys_eq!(migrated.reward_token_mint, ctx.accounts.reward_token_mint.key(), CustomError::InvalidRewardMint);
+
+        write_migrated_account(&rewards_config_info, &ctx.accounts.admin, &ctx.accounts.system_program, RewardsConfig::LEN, &migrated)?;
+
+        msg!("Migrated rewards config to version {}", ACCOUNT_VERSION);
+        Ok(())
+    }
+
+    /// Registers a merchant signer that may record customer spend for earn-on-spend rewards.
+    pub fn register_merchant(ctx: Context<RegisterMerchant>, name: String) -> Result<()> {
+        require!(!name.is_empty() && name.len() <= MAX_MERCHANT_NAME_LEN, CustomError::InvalidMerchantName);
+
+        let merchant = &mut ctx.accounts.merchant;
+        merchant.version = ACCOUNT_VERSION;
+        merchant.authority = ctx.accounts.merchant_authority.key();
+        merchant.name = name;
+        merchant.is_active = true;
+        merchant.total_spend = 0;
+        merchant.total_rewarded = 0;
+        merchant.day = 0;
+        merchant.rewarded_today = 0;
+        merchant.registered_at = Clock::get()?.unix_timestamp;
+        merchant.bump = ctx.bumps.merchant;
+
+        msg!("Merchant {} registered with signer {}", merchant.name, merchant.authority);
+        Ok(())
     }
+
+    /// Enables or disables a registered merchant.
+    pub fn set_merchant_active(ctx: Context<SetMerchantActive>, is_active: bool) -> Result<()> {
+        let merchant = &mut ctx.accounts.merchant;
+        merchant.is_active = is_active;
+
+        msg!("Merchant {} active: {}", merchant.name, is_active);
+        Ok(())
+    }
+
+    /// Records a customer purchase at a registered merchant and credits earn-on-spend rewards.
+    /// `amount` is the spend in USDT base units; the customer earns `earn_ratio` bps of it in reward
+    /// tokens, one whole token per whole USDT, at the card's current earn ratio.
+    /// The customer proves a card of `nft` with a `LoyaltyCard` or, for compressed cards,
+    /// `card_proof` (its proof nodes first in the remaining accounts).
+    /// A customer may be credited at most `max_rewards_per_user` per day across all merchants,
+    /// each merchant at most `MERCHANT_DAILY_CAP_MULTIPLIER` times that per day across all customers,
+    /// and a receipt PDA keyed by `receipt_id` makes every receipt single-use.
+    pub fn record_spend<'info>(
+        ctx: Context<'_, '_, '_, 'info, RecordSpend<'info>>,
+        receipt_id: [u8; 32],
+        amount: u64,
+        card_proof: Option<CompressedCardProof>,
+    ) -> Result<()> {
+        let merkle_tree = ctx.accounts.merkle_tree.as_ref().map(|tree| tree.to_account_info());
+        let compression_program = ctx.accounts.compression_program.as_ref().map(|program| program.to_account_info());
+        let (card_nft, _) = verify_card_holder(
+            ctx.accounts.customer.key(),
+            ctx.accounts.loyalty_card.as_deref(),
+            ctx.accounts.card_tree.as_deref(),
+            merkle_tree.as_ref(),
+            compression_program.as_ref(),
+            card_proof.as_ref(),
+            ctx.remaining_accounts,
+        )?;
+        require!(card_nft == ctx.accounts.nft.key(), CustomError::CardHolderRequired);
+
+        let (card, earn_ratio) = match (ctx.accounts.loyalty_card.as_deref(), card_proof.as_ref(), merkle_tree.as_ref()) {
+            (Some(loyalty_card), _, _) => (loyalty_card.mint, loyalty_card.earn_ratio),
+            // Compressed cards cannot evolve, so they earn at their NFT's base ratio.
+            (None, Some(card_proof), Some(merkle_tree)) => (
+                mpl_bubblegum::utils::get_asset_id(merkle_tree.key, card_proof.nonce),
+                ctx.accounts.nft.earn_on_spend_ratio,
+            ),
+            _ => return err!(CustomError::CardHolderRequired),
+        };
+
+        let rewards_config = &ctx.accounts.rewards_config;
+        let current_timestamp = Clock::get()?.unix_timestamp;
+
+        require!(rewards_config.is_active, CustomError::RewardsInactive);
+        require!(ctx.accounts.merchant.is_active, CustomError::MerchantInactive);
+        require!(amount > 0, CustomError::ZeroSpendAmount);
+
+        let earned = spend_reward_amount(amount, earn_ratio, ctx.accounts.usdt_mint.decimals, ctx.accounts.reward_mint.decimals)?;
+
+        let today = current_timestamp.div_euclid(SECONDS_PER_DAY);
+        let spend_tracker: &mut SpendTracker = &mut ctx.accounts.spend_tracker;
+        let customer_remaining = daily_reward_remaining(
+            &mut spend_tracker.day,
+            &mut spend_tracker.rewarded_today,
+            rewards_config.max_rewards_per_user,
+            today,
+        );
+        let merchant: &mut Merchant = &mut ctx.accounts.merchant;
+        let merchant_remaining = daily_reward_remaining(
+            &mut merchant.day,
+            &mut merchant.rewarded_today,
+            merchant_daily_reward_cap(rewards_config.max_rewards_per_user),
+            today,
+        );
+        require!(earned == 0 || customer_remaining > 0, CustomError::DailyRewardCapReached);
+        require!(earned == 0 || merchant_remaining > 0, CustomError::MerchantRewardCapReached);
+        let reward = earned.min(customer_remaining).min(merchant_remaining);
+
+        if reward > 0 {
+            let committed = rewards_config.outstanding_income
+                .checked_add(reward)
+                .ok_or(CustomError::InsufficientRewardFunds)?;
+            require!(ctx.accounts.reward_vault.amount >= committed, CustomError::InsufficientRewardFunds);
+
+            let seeds = &[b"rewards_config".as_ref(), &[rewards_config.bump]];
+            let signer = &[&seeds[..]];
+
+            let cpi_accounts = Transfer {
+                from: ctx.accounts.reward_vault.to_account_info(),
+                to: ctx.accounts.customer_reward_account.to_account_info(),
+                authority: ctx.accounts.rewards_config.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+            token::transfer(cpi_ctx, reward)?;
+        }
+
+        let spend_tracker = &mut ctx.accounts.spend_tracker;
+        spend_tracker.version = ACCOUNT_VERSION;
+        spend_tracker.customer = ctx.accounts.customer.key();
+        spend_tracker.rewarded_today += reward;
+        spend_tracker.bump = ctx.bumps.spend_tracker;
+
+        let merchant = &mut ctx.accounts.merchant;
+        merchant.total_spend += amount;
+        merchant.total_rewarded += reward;
+        merchant.rewarded_today += reward;
+
+        let receipt = &mut ctx.accounts.receipt;
+        receipt.version = ACCOUNT_VERSION;
+        receipt.merchant = merchant.key();
+        receipt.customer = ctx.accounts.customer.key();
+        receipt.receipt_id = receipt_id;
+        receipt.amount = amount;
+        receipt.reward = reward;
+        receipt.recorded_at = current_timestamp;
+        receipt.bump = ctx.bumps.receipt;
+
+        emit!(SpendRecorded {
+            merchant: merchant.key(),
+            customer: receipt.customer,
+            card,
+            receipt_id,
+            amount,
+            reward,
+            timestamp: current_timestamp,
+        });
+
+        msg!("Merchant {} recorded spend of {}; customer earned {}", merchant.name, amount, reward);
+        Ok(())
+    }
+
+    /// Creates the Metaplex sized collection that loyalty cards of `collection` are verified into.
+    /// The collection mint and its metadata are controlled by the `card_authority` PDA.
+    pub fn initialize_collection(
+        ctx: Context<InitializeCollection>,
+        collection: Collection,
+        symbol: String,
+        uri: String,
+    ) -> Result<()> {
+        require!(
+            symbol.len() <= MAX_METADATA_SYMBOL_LEN && uri.len() <= MAX_METADATA_URI_LEN,
+            CustomError::MetadataFieldTooLong
+        );
+
+        let seeds = &[b"card_authority".as_ref(), &[ctx.bumps.card_authority]];
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = MintTo {
+            mint: ctx.accounts.collection_mint.to_account_info(),
+            to: ctx.accounts.collection_token_account.to_account_info(),
+            authority: ctx.accounts.card_authority.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::mint_to(cpi_ctx, 1)?;
+
+        let cpi_accounts = CreateMetadataAccountsV3 {
+            metadata: ctx.accounts.collection_metadata.to_account_info(),
+            mint: ctx.accounts.collection_mint.to_account_info(),
+            mint_authority: ctx.accounts.card_authority.to_account_info(),
+            payer: ctx.accounts.admin_authority.to_account_info(),
+            update_authority: ctx.accounts.card_authority.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            rent: ctx.accounts.rent.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_metadata_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token_metadata::create_metadata_accounts_v3(
+            cpi_ctx,
+            DataV2 {
+                name: collection.as_str().to_string(),
+                symbol,
+                uri,
+                seller_fee_basis_points: 0,
+                creators: None,
+                collection: None,
+                uses: None,
+            },
+            true,
+            true,
+            Some(CollectionDetails::V1 { size: 0 }),
+        )?;
+
+        let cpi_accounts = CreateMasterEditionV3 {
+            edition: ctx.accounts.collection_master_edition.to_account_info(),
+            mint: ctx.accounts.collection_mint.to_account_info(),
+            update_authority: ctx.accounts.card_authority.to_account_info(),
+            mint_authority: ctx.accounts.card_authority.to_account_info(),
+            payer: ctx.accounts.admin_authority.to_account_info(),
+            metadata: ctx.accounts.collection_metadata.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            rent: ctx.accounts.rent.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_metadata_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token_metadata::create_master_edition_v3(cpi_ctx, Some(0))?;
+
+        let card_collection = &mut ctx.accounts.card_collection;
+        card_collection.version = ACCOUNT_VERSION;
+        card_collection.collection = collection;
+        card_collection.mint = ctx.accounts.collection_mint.key();
+        card_collection.size = 0;
+        card_collection.bump = ctx.bumps.card_collection;
+
+        msg!("Loyalty card collection {} created", collection.as_str());
+        Ok(())
+    }
+
+    /// Mints a loyalty card as a 0-decimal Metaplex NFT with a master edition.
+    /// Metadata comes from the `NftAccount` (`display_name`, `symbol`, `uri`), the card is verified
+    /// into its collection, and the buyer pays `buy_price_usdt` into the treasury.
+    /// Every card is frozen in the holder's wallet. Custodial cards stay soulbound; non-custodial
+    /// cards move only through `transfer_card`, so the `LoyaltyCard` owner always matches the token.
+    pub fn mint_loyalty_card(ctx: Context<MintLoyaltyCard>) -> Result<()> {
+        let nft = &ctx.accounts.nft;
+        require!(nft.is_active, CustomError::NftNotActive);
+        require!(nft.minted_count < nft.mint_quantity, CustomError::MintQuantityExhausted);
+
+        let price = nft.buy_price_usdt;
+        let card_data = card_metadata(nft, ctx.accounts.collection_mint.key())?;
+
+        if price > 0 {
+            let cpi_accounts = Transfer {
+                from: ctx.accounts.owner_usdt_account.to_account_info(),
+                to: ctx.accounts.treasury_vault.to_account_info(),
+                authority: ctx.accounts.owner.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+            token::transfer(cpi_ctx, price)?;
+
+            let treasury_asset = &mut ctx.accounts.treasury_asset;
+            treasury_asset.balance += price;
+            treasury_asset.total_deposited += price;
+
+            emit!(TreasuryDeposit {
+                mint: treasury_asset.mint,
+                from: ctx.accounts.owner.key(),
+                amount: price,
+                balance: treasury_asset.balance,
+                timestamp: Clock::get()?.unix_timestamp,
+            });
+        }
+
+        mint_card_nft(
+            CardMintAccounts {
+                card_mint: ctx.accounts.card_mint.to_account_info(),
+                owner_card_account: ctx.accounts.owner_card_account.to_account_info(),
+                card_metadata: ctx.accounts.card_metadata.to_account_info(),
+                card_master_edition: ctx.accounts.card_master_edition.to_account_info(),
+                collection_mint: ctx.accounts.collection_mint.to_account_info(),
+                collection_metadata: ctx.accounts.collection_metadata.to_account_info(),
+                collection_master_edition: ctx.accounts.collection_master_edition.to_account_info(),
+                card_authority: ctx.accounts.card_authority.to_account_info(),
+                payer: ctx.accounts.owner.to_account_info(),
+                token_program: ctx.accounts.token_program.to_account_info(),
+                token_metadata_program: ctx.accounts.token_metadata_program.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+                rent: ctx.accounts.rent.to_account_info(),
+            },
+            card_data,
+            ctx.bumps.card_authority,
+        )?;
+
+        freeze_card(
+            ctx.accounts.card_mint.to_account_info(),
+            ctx.accounts.owner_card_account.to_account_info(),
+            ctx.accounts.card_master_edition.to_account_info(),
+            ctx.accounts.card_authority.to_account_info(),
+            ctx.accounts.owner.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.token_metadata_program.to_account_info(),
+            ctx.bumps.card_authority,
+        )?;
+
+        let loyalty_card = &mut ctx.accounts.loyalty_card;
+        loyalty_card.version = ACCOUNT_VERSION;
+        loyalty_card.owner = ctx.accounts.owner.key();
+        loyalty_card.nft = ctx.accounts.nft.key();
+        loyalty_card.mint = ctx.accounts.card_mint.key();
+        loyalty_card.status = CardStatus::Active as u8;
+        loyalty_card.upgraded_to = None;
+        loyalty_card.evolved = false;
+        loyalty_card.earn_ratio = ctx.accounts.nft.earn_on_spend_ratio;
+        loyalty_card.minted_at = Clock::get()?.unix_timestamp;
+        loyalty_card.bump = ctx.bumps.loyalty_card;
+
+        ctx.accounts.card_collection.size += 1;
+
+        let nft = &mut ctx.accounts.nft;
+        nft.minted_count += 1;
+
+        msg!("Minted {} card {} to {} ({}/{})", nft.nft_name, loyalty_card.mint, loyalty_card.owner, nft.minted_count, nft.mint_quantity);
+        Ok(())
+    }
+
+    /// Upgrades a custodial card to a higher tier. The old card is thawed, burned and its record kept
+    /// as `Upgraded`; the holder pays the `buy_price_usdt` difference into the treasury and
+    /// receives the target tier's `upgrade_bonus_ratio` of that difference as RAC from the reward vault.
+    /// The new card is frozen like any other custodial card. Compressed cards have no `LoyaltyCard`
+    /// record and cannot be upgraded; their holders mint the higher tier with `mint_loyalty_card`.
+    pub fn upgrade_card(ctx: Context<UpgradeCard>) -> Result<()> {
+        let old_nft = &ctx.accounts.old_nft;
+        let new_nft = &ctx.accounts.new_nft;
+
+        require!(ctx.accounts.old_card.status == CardStatus::Active as u8, CustomError::CardNotActive);
+        require!(
+            old_nft.custody_type == CustodyType::Custodial && old_nft.is_upgradeable,
+            CustomError::UpgradeNotAllowed
+        );
+        require!(new_nft.custody_type == CustodyType::Custodial, CustomError::InvalidCustodyType);
+        require!(new_nft.is_active, CustomError::NftNotActive);
+        require!(new_nft.minted_count < new_nft.mint_quantity, CustomError::MintQuantityExhausted);
+        require!(new_nft.buy_price_usdt > old_nft.buy_price_usdt, CustomError::UpgradeNotHigherTier);
+
+        let price_difference = new_nft.buy_price_usdt - old_nft.buy_price_usdt;
+        let rac_bonus = upgrade_bonus_amount(
+            price_difference,
+            new_nft.upgrade_bonus_ratio,
+            ctx.accounts.usdt_mint.decimals,
+        )?;
+        let card_data = card_metadata(new_nft, ctx.accounts.collection_mint.key())?;
+        let current_timestamp = Clock::get()?.unix_timestamp;
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.owner_usdt_account.to_account_info(),
+            to: ctx.accounts.treasury_vault.to_account_info(),
+            authority: ctx.accounts.owner.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        token::transfer(cpi_ctx, price_difference)?;
+
+        let treasury_asset = &mut ctx.accounts.treasury_asset;
+        treasury_asset.balance += price_difference;
+        treasury_asset.total_deposited += price_difference;
+
+        emit!(TreasuryDeposit {
+            mint: treasury_asset.mint,
+            from: ctx.accounts.owner.key(),
+            amount: price_difference,
+            balance: treasury_asset.balance,
+            timestamp: current_timestamp,
+        });
+
+        // Cards minted before custodial cards were frozen are still transferable and need no thaw.
+        if ctx.accounts.old_card_token_account.is_frozen() {
+            thaw_card(
+                ctx.accounts.old_card_mint.to_account_info(),
+                ctx.accounts.old_card_token_account.to_account_info(),
+                ctx.accounts.old_card_master_edition.to_account_info(),
+                ctx.accounts.card_authority.to_account_info(),
+                ctx.accounts.token_program.to_account_info(),
+                ctx.accounts.token_metadata_program.to_account_info(),
+                ctx.bumps.card_authority,
+            )?;
+        }
+
+        let cpi_accounts = BurnNft {
+            metadata: ctx.accounts.old_card_metadata.to_account_info(),
+            owner: ctx.accounts.owner.to_account_info(),
+            mint: ctx.accounts.old_card_mint.to_account_info(),
+            token: ctx.accounts.old_card_token_account.to_account_info(),
+            edition: ctx.accounts.old_card_master_edition.to_account_info(),
+            spl_token: ctx.accounts.token_program.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_metadata_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts)
+            .with_remaining_accounts(vec![ctx.accounts.old_collection_metadata.to_account_info()]);
+        token_metadata::burn_nft(cpi_ctx, Some(ctx.accounts.old_collection_metadata.key()))?;
+
+        mint_card_nft(
+            CardMintAccounts {
+                card_mint: ctx.accounts.card_mint.to_account_info(),
+                owner_card_account: ctx.accounts.owner_card_account.to_account_info(),
+                card_metadata: ctx.accounts.card_metadata.to_account_info(),
+                card_master_edition: ctx.accounts.card_master_edition.to_account_info(),
+                collection_mint: ctx.accounts.collection_mint.to_account_info(),
+                collection_metadata: ctx.accounts.collection_metadata.to_account_info(),
+                collection_master_edition: ctx.accounts.collection_master_edition.to_account_info(),
+                card_authority: ctx.accounts.card_authority.to_account_info(),
+                payer: ctx.accounts.owner.to_account_info(),
+                token_program: ctx.accounts.token_program.to_account_info(),
+                token_metadata_program: ctx.accounts.token_metadata_program.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+                rent: ctx.accounts.rent.to_account_info(),
+            },
+            card_data,
+            ctx.bumps.card_authority,
+        )?;
+
+        freeze_card(
+            ctx.accounts.card_mint.to_account_info(),
+            ctx.accounts.owner_card_account.to_account_info(),
+            ctx.accounts.card_master_edition.to_account_info(),
+            ctx.accounts.card_authority.to_account_info(),
+            ctx.accounts.owner.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.token_metadata_program.to_account_info(),
+            ctx.bumps.card_authority,
+        )?;
+
+        if rac_bonus > 0 {
+            let committed = ctx.accounts.rewards_config.outstanding_income
+                .checked_add(rac_bonus)
+                .ok_or(CustomError::InsufficientRewardFunds)?;
+            require!(ctx.accounts.reward_vault.amount >= committed, CustomError::InsufficientRewardFunds);
+
+            let seeds = &[b"rewards_config".as_ref(), &[ctx.accounts.rewards_config.bump]];
+            let signer = &[&seeds[..]];
+
+            let cpi_accounts = Transfer {
+                from: ctx.accounts.reward_vault.to_account_info(),
+                to: ctx.accounts.owner_rac_account.to_account_info(),
+                authority: ctx.accounts.rewards_config.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+            token::transfer(cpi_ctx, rac_bonus)?;
+        }
+
+        let new_mint = ctx.accounts.card_mint.key();
+
+        let new_card = &mut ctx.accounts.new_card;
+        new_card.version = ACCOUNT_VERSION;
+        new_card.owner = ctx.accounts.owner.key();
+        new_card.nft = ctx.accounts.new_nft.key();
+        new_card.mint = new_mint;
+        new_card.status = CardStatus::Active as u8;
+        new_card.upgraded_to = None;
+        new_card.evolved = false;
+        new_card.earn_ratio = ctx.accounts.new_nft.earn_on_spend_ratio;
+        new_card.minted_at = current_timestamp;
+        new_card.bump = ctx.bumps.new_card;
+
+        let old_card = &mut ctx.accounts.old_card;
+        old_card.status = CardStatus::Upgraded as u8;
+        old_card.upgraded_to = Some(new_mint);
+
+        ctx.accounts.card_collection.size += 1;
+        ctx.accounts.new_nft.minted_count += 1;
+
+        emit!(CardUpgraded {
+            owner: ctx.accounts.owner.key(),
+            old_mint: old_card.mint,
+            new_mint,
+            from_nft: ctx.accounts.old_nft.key(),
+            to_nft: ctx.accounts.new_nft.key(),
+            price_paid: price_difference,
+            rac_bonus,
+            timestamp: current_timestamp,
+        });
+
+        msg!("Card {} upgraded to {} ({})", old_card.mint, new_mint, ctx.accounts.new_nft.nft_name);
+        Ok(())
+    }
+
+    /// Transfers a non-custodial card to another wallet. The card is thawed, moved and frozen
+    /// again in the recipient's wallet, which is why the recipient signs too. The card's record,
+    /// including its evolution and earn ratio, follows the token to `recipient`. Investment income
+    /// and reward stakes belong to the wallet (`investment`, `reward_stake`), not the card, so they
+    /// stay with the sender. Custodial cards are soulbound and cannot be transferred.
+    pub fn transfer_card(ctx: Context<TransferCard>) -> Result<()> {
+        let loyalty_card = &ctx.accounts.loyalty_card;
+        require!(loyalty_card.status == CardStatus::Active as u8, CustomError::CardNotActive);
+        require!(ctx.accounts.nft.custody_type == CustodyType::NonCustodial, CustomError::CardNotTransferable);
+        require!(ctx.accounts.recipient.key() != ctx.accounts.owner.key(), CustomError::InvalidCardRecipient);
+
+        // Cards minted before non-custodial cards were frozen need no thaw.
+        if ctx.accounts.owner_card_account.is_frozen() {
+            thaw_card(
+                ctx.accounts.card_mint.to_account_info(),
+                ctx.accounts.owner_card_account.to_account_info(),
+                ctx.accounts.card_master_edition.to_account_info(),
+                ctx.accounts.card_authority.to_account_info(),
+                ctx.accounts.token_program.to_account_info(),
+                ctx.accounts.token_metadata_program.to_account_info(),
+                ctx.bumps.card_authority,
+            )?;
+        }
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.owner_card_account.to_account_info(),
+            to: ctx.accounts.recipient_card_account.to_account_info(),
+            authority: ctx.accounts.owner.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        token::transfer(cpi_ctx, 1)?;
+
+        freeze_card(
+            ctx.accounts.card_mint.to_account_info(),
+            ctx.accounts.recipient_card_account.to_account_info(),
+            ctx.accounts.card_master_edition.to_account_info(),
+            ctx.accounts.card_authority.to_account_info(),
+            ctx.accounts.recipient.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.token_metadata_program.to_account_info(),
+            ctx.bumps.card_authority,
+        )?;
+
+        let loyalty_card = &mut ctx.accounts.loyalty_card;
+        loyalty_card.owner = ctx.accounts.recipient.key();
+
+        emit!(CardTransferred {
+            from: ctx.accounts.owner.key(),
+            to: loyalty_card.owner,
+            mint: loyalty_card.mint,
+            nft: loyalty_card.nft,
+            evolved: loyalty_card.evolved,
+            earn_ratio: loyalty_card.earn_ratio,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Card {} transferred from {} to {}", loyalty_card.mint, ctx.accounts.owner.key(), loyalty_card.owner);
+        Ok(())
+    }
+
+    /// Sets the metadata URI that cards of an evolvable NFT switch to when evolved.
+    pub fn set_evolved_uri(ctx: Context<SetEvolvedUri>, evolved_uri: String) -> Result<()> {
+        require!(evolved_uri.len() <= MAX_METADATA_URI_LEN, CustomError::MetadataFieldTooLong);
+
+        let nft = &mut ctx.accounts.nft;
+        nft.evolved_uri = evolved_uri;
+        nft.updated_at = Clock::get()?.unix_timestamp;
+
+        msg!("Evolved URI for {} set to {}", nft.nft_name, nft.evolved_uri);
+        Ok(())
+    }
+
+    /// Evolves a card once its holder has invested or staked RAC worth `evolution_min_investment` USDT,
+    /// one whole RAC per whole USDT: veRAC locks, reward stakes and USDT investments in the card's NFT all count.
+    /// The card's metadata switches to the NFT's `evolved_uri` and it earns at `evolution_earnings_ratio`.
+    pub fn evolve_card(ctx: Context<EvolveCard>) -> Result<()> {
+        let nft = &ctx.accounts.nft;
+        let current_timestamp = Clock::get()?.unix_timestamp;
+        let invested_amount = evolution_holdings(
+            &ctx.accounts.loyalty_card.owner,
+            nft,
+            ctx.accounts.ve_lock.as_deref(),
+            ctx.accounts.reward_stake.as_deref(),
+            ctx.accounts.custodial_investment.as_deref(),
+            ctx.accounts.fractional_investment.as_deref().zip(ctx.accounts.fractional_nft.as_deref()),
+            ctx.accounts.usdt_mint.decimals,
+            current_timestamp,
+        )?;
+        let eligibility = evolution_eligibility(&ctx.accounts.loyalty_card, nft, invested_amount, ctx.accounts.usdt_mint.decimals)?;
+
+        require!(eligibility.is_active, CustomError::CardNotActive);
+        require!(eligibility.is_evolvable, CustomError::EvolutionNotAllowed);
+        require!(!eligibility.already_evolved, CustomError::CardAlreadyEvolved);
+        require!(!nft.evolved_uri.is_empty(), CustomError::EvolvedUriNotSet);
+        require!(eligibility.eligible, CustomError::InsufficientEvolutionStake);
+
+        let mut data = card_metadata(nft, ctx.accounts.card_collection.mint)?;
+        data.uri = nft.evolved_uri.clone();
+        // The card is already verified into its collection; keep it that way.
+        data.collection = Some(MetadataCollection {
+            verified: true,
+            key: ctx.accounts.card_collection.mint,
+        });
+
+        let seeds = &[b"card_authority".as_ref(), &[ctx.bumps.card_authority]];
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = UpdateMetadataAccountsV2 {
+            metadata: ctx.accounts.card_metadata.to_account_info(),
+            update_authority: ctx.accounts.card_authority.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_metadata_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token_metadata::update_metadata_accounts_v2(cpi_ctx, None, Some(data), None, None)?;
+
+        let loyalty_card = &mut ctx.accounts.loyalty_card;
+        loyalty_card.evolved = true;
+        loyalty_card.earn_ratio = nft.evolution_earnings_ratio;
+
+        emit!(CardEvolved {
+            owner: loyalty_card.owner,
+            mint: loyalty_card.mint,
+            nft: nft.key(),
+            invested_amount: eligibility.invested_amount,
+            earn_ratio: loyalty_card.earn_ratio,
+            timestamp: current_timestamp,
+        });
+
+        msg!("Card {} evolved; earn ratio now {}", loyalty_card.mint, loyalty_card.earn_ratio);
+        Ok(())
+    }
+
+    /// Reports whether a card can evolve and how much more RAC its holder needs to invest or stake.
+    pub fn get_evolution_eligibility(ctx: Context<GetEvolutionEligibility>) -> Result<EvolutionEligibility> {
+        let invested_amount = evolution_holdings(
+            &ctx.accounts.loyalty_card.owner,
+            &ctx.accounts.nft,
+            ctx.accounts.ve_lock.as_deref(),
+            ctx.accounts.reward_stake.as_deref(),
+            ctx.accounts.custodial_investment.as_deref(),
+            ctx.accounts.fractional_investment.as_deref().zip(ctx.accounts.fractional_nft.as_deref()),
+            ctx.accounts.usdt_mint.decimals,
+            Clock::get()?.unix_timestamp,
+        )?;
+        evolution_eligibility(&ctx.accounts.loyalty_card, &ctx.accounts.nft, invested_amount, ctx.accounts.usdt_mint.decimals)
+    }
+
+    /// Raises or lowers an NFT's mint cap. Requires the DAO authority, so it only runs through
+    /// an approved proposal, and the cap can never drop below the cards already minted.
+    pub fn set_mint_quantity(ctx: Context<SetMintQuantity>, mint_quantity: u64) -> Result<()> {
+        let nft = &mut ctx.accounts.nft;
+        require!(mint_quantity >= nft.minted_count, CustomError::MintQuantityBelowMinted);
+
+        let previous = nft.mint_quantity;
+        nft.mint_quantity = mint_quantity;
+        nft.updated_at = Clock::get()?.unix_timestamp;
+
+        msg!("Mint cap for {} changed from {} to {} ({} minted)", nft.nft_name, previous, mint_quantity, nft.minted_count);
+        Ok(())
+    }
+
+    /// Registers a concurrent Merkle tree for compressed cards of a free custodial NFT.
+    /// The client allocates `merkle_tree` (owned by the account compression program) beforehand;
+    /// this creates the Bubblegum tree config with the program's `card_tree_authority` as creator.
+    pub fn create_card_tree(ctx: Context<CreateCardTree>, max_depth: u32, max_buffer_size: u32) -> Result<()> {
+        let nft = &ctx.accounts.nft;
+        require!(nft.custody_type == CustodyType::Custodial, CustomError::InvalidCustodyType);
+        require!(nft.buy_price_usdt == 0, CustomError::CompressedCardNotFree);
+        require!(max_depth > 0 && max_depth <= 30, CustomError::InvalidTreeDepth);
+
+        let seeds = &[b"card_tree_authority".as_ref(), &[ctx.bumps.tree_authority]];
+        let signer = &[&seeds[..]];
+
+        CreateTreeConfigCpiBuilder::new(&ctx.accounts.bubblegum_program.to_account_info())
+            .tree_config(&ctx.accounts.tree_config.to_account_info())
+            .merkle_tree(&ctx.accounts.merkle_tree.to_account_info())
+            .payer(&ctx.accounts.admin_authority.to_account_info())
+            .tree_creator(&ctx.accounts.tree_authority.to_account_info())
+            .log_wrapper(&ctx.accounts.log_wrapper.to_account_info())
+            .compression_program(&ctx.accounts.compression_program.to_account_info())
+            .system_program(&ctx.accounts.system_program.to_account_info())
+            .max_depth(max_depth)
+            .max_buffer_size(max_buffer_size)
+            .public(false)
+            .invoke_signed(signer)?;
+
+        let card_tree = &mut ctx.accounts.card_tree;
+        card_tree.version = ACCOUNT_VERSION;
+        card_tree.nft = nft.key();
+        card_tree.collection_mint = ctx.accounts.card_collection.mint;
+        card_tree.merkle_tree = ctx.accounts.merkle_tree.key();
+        card_tree.capacity = 1u64 << max_depth;
+        card_tree.minted = 0;
+        card_tree.bump = ctx.bumps.card_tree;
+
+        msg!("Card tree {} created for {} with capacity {}", card_tree.merkle_tree, nft.nft_name, card_tree.capacity);
+        Ok(())
+    }
+
+    /// Mints a free custodial card as a compressed NFT into the program's tree and its collection.
+    /// No token or metadata accounts are created; the leaf index is the card's nonce.
+    /// The leaf is owned and delegated to the holder's `card_custody` PDA. Only this program can
+    /// sign for it and no instruction does, so the card is soulbound like a frozen Metaplex card.
+    pub fn mint_compressed_card(ctx: Context<MintCompressedCard>) -> Result<()> {
+        let nft = &ctx.accounts.nft;
+        let card_tree = &ctx.accounts.card_tree;
+        require!(nft.is_active, CustomError::NftNotActive);
+        require!(nft.custody_type == CustodyType::Custodial, CustomError::InvalidCustodyType);
+        require!(nft.buy_price_usdt == 0, CustomError::CompressedCardNotFree);
+        require!(nft.minted_count < nft.mint_quantity, CustomError::MintQuantityExhausted);
+        require!(card_tree.minted < card_tree.capacity, CustomError::CardTreeFull);
+        require!(
+            nft.display_name.len() <= MAX_METADATA_NAME_LEN
+                && nft.symbol.len() <= MAX_METADATA_SYMBOL_LEN
+                && nft.uri.len() <= MAX_METADATA_URI_LEN,
+            CustomError::MetadataFieldTooLong
+        );
+
+        let metadata = MetadataArgs {
+            name: nft.display_name.clone(),
+            symbol: nft.symbol.clone(),
+            uri: nft.uri.clone(),
+            seller_fee_basis_points: 0,
+            primary_sale_happened: true,
+            is_mutable: true,
+            edition_nonce: None,
+            token_standard: Some(TokenStandard::NonFungible),
+            collection: Some(BubblegumCollection {
+                verified: false,
+                key: ctx.accounts.collection_mint.key(),
+            }),
+            uses: None,
+            token_program_version: TokenProgramVersion::Original,
+            creators: vec![],
+        };
+
+        let tree_seeds = &[b"card_tree_authority".as_ref(), &[ctx.bumps.tree_authority]];
+        let card_seeds = &[b"card_authority".as_ref(), &[ctx.bumps.card_authority]];
+        let signer = &[&tree_seeds[..], &card_seeds[..]];
+
+        MintToCollectionV1CpiBuilder::new(&ctx.accounts.bubblegum_program.to_account_info())
+            .tree_config(&ctx.accounts.tree_config.to_account_info())
+            .leaf_owner(&ctx.accounts.card_custody.to_account_info())
+            .leaf_delegate(&ctx.accounts.card_custody.to_account_info())
+            .merkle_tree(&ctx.accounts.merkle_tree.to_account_info())
+            .payer(&ctx.accounts.owner.to_account_info())
+            .tree_creator_or_delegate(&ctx.accounts.tree_authority.to_account_info())
+            .collection_authority(&ctx.accounts.card_authority.to_account_info())
+            .collection_authority_record_pda(Some(&ctx.accounts.bubblegum_program.to_account_info()))
+            .collection_mint(&ctx.accounts.collection_mint.to_account_info())
+            .collection_metadata(&ctx.accounts.collection_metadata.to_account_info())
+            .collection_edition(&ctx.accounts.collection_master_edition.to_account_info())
+            .bubblegum_signer(&ctx.accounts.bubblegum_signer.to_account_info())
+            .log_wrapper(&ctx.accounts.log_wrapper.to_account_info())
+            .compression_program(&ctx.accounts.compression_program.to_account_info())
+            .token_metadata_program(&ctx.accounts.token_metadata_program.to_account_info())
+            .system_program(&ctx.accounts.system_program.to_account_info())
+            .metadata(metadata)
+            .invoke_signed(signer)?;
+
+        let nonce = ctx.accounts.card_tree.minted;
+        let merkle_tree = ctx.accounts.merkle_tree.key();
+
+        ctx.accounts.card_tree.minted += 1;
+        ctx.accounts.card_collection.size += 1;
+        let nft = &mut ctx.accounts.nft;
+        nft.minted_count += 1;
+
+        emit!(CompressedCardMinted {
+            nft: nft.key(),
+            merkle_tree,
+            asset_id: mpl_bubblegum::utils::get_asset_id(&merkle_tree, nonce),
+            owner: ctx.accounts.owner.key(),
+            nonce,
+        });
+
+        msg!("Minted compressed {} card #{} to {}", nft.nft_name, nonce, ctx.accounts.owner.key());
+        Ok(())
+    }
+
+    /// Proves that the signer holds a compressed card. The Merkle proof is passed as
+    /// remaining accounts, ordered from leaf to root, as returned by the DAS `getAssetProof` API.
+    pub fn verify_compressed_card<'info>(
+        ctx: Context<'_, '_, '_, 'info, VerifyCompressedCard<'info>>,
+        card_proof: CompressedCardProof,
+    ) -> Result<()> {
+        let nonce = card_proof.nonce;
+        verify_compressed_holder(
+            &ctx.accounts.compression_program.to_account_info(),
+            &ctx.accounts.merkle_tree.to_account_info(),
+            ctx.remaining_accounts,
+            ctx.accounts.holder.key(),
+            &card_proof,
+        )?;
+
+        emit!(CompressedCardVerified {
+            nft: ctx.accounts.card_tree.nft,
+            merkle_tree: ctx.accounts.merkle_tree.key(),
+            holder: ctx.accounts.holder.key(),
+            nonce,
+        });
+
+        msg!("Verified {} holds compressed card #{}", ctx.accounts.holder.key(), nonce);
+        Ok(())
+    }
+
+    /// Allows the DAO admin to update the passive income rate of an NFT.
+    pub fn update_passive_income_rate(ctx: Context<UpdateRate>, new_rate: u64) -> Result<()> {
+        let nft_account = &mut ctx.accounts.nft;
+        nft_account.passive_income_rate = new_rate;
+
+        msg!("Passive income rate for NFT updated to {}", new_rate);
+        Ok(())
+    }
+
+    
+    /// Allows a user to create a new proposal.
+    pub fn create_proposal(ctx: Context<CreateProposal>, dao_org: u8, proposal_type: u8, description: String) -> Result<()> {
+        let dao_config = &mut ctx.accounts.dao_config;
+        let proposal_account = &mut ctx.accounts.proposal;
+
+        check_proposal_threshold(ctx.accounts.proposer_rac_account.amount, dao_config)?;
+        // Governance parameters can only change through `create_dao_config_proposal`
+        require!(proposal_type != ProposalType::DaoConfigUpdate as u8, CustomError::InvalidProposalType);
+
+        dao_config.proposal_count += 1;
+        proposal_account.version = ACCOUNT_VERSION;
+        proposal_account.proposal_id = dao_config.proposal_count;
+        proposal_account.proposer = ctx.accounts.proposer.key();
+        proposal_account.proposal_type = proposal_type;
+        proposal_account.description = description;
+        proposal_account.votes_for = 0;
+        proposal_account.votes_against = 0;
+        proposal_account.votes_abstain = 0;
+        proposal_account.dao_org = dao_org;
+        proposal_account.status = ProposalStatus::Draft as u8;
+        proposal_account.created_at = Clock::get()?.unix_timestamp;
+        proposal_account.end_slot = Clock::get()?.slot + dao_config.voting_period_slots;
+        proposal_account.executed_at = None;
+        proposal_account.instruction_count = 0;
+        proposal_account.instructions_executed = 0;
+        proposal_account.eta = 0;
+        
+        msg!("New proposal created: {}", proposal_account.description);
+        Ok(())
+    }
+
+    /// Allows a user to create a rewards configuration proposal.
+    pub fn create_config_proposal(
+        ctx: Context<CreateConfigProposal>,
+        dao_org: u8,
+        proposed_distribution_interval: u64,
+        proposed_max_rewards_per_user: u64,
+        description: String
+    ) -> Result<()> {
+        let dao_config = &mut ctx.accounts.dao_config;
+        let proposal_account = &mut ctx.accounts.proposal;
+        let config_proposal = &mut ctx.accounts.config_proposal;
+
+        check_proposal_threshold(ctx.accounts.proposer_rac_account.amount, dao_config)?;
+
+        dao_config.proposal_count += 1;
+        proposal_account.version = ACCOUNT_VERSION;
+        proposal_account.proposal_id = dao_config.proposal_count;
+        proposal_account.proposer = ctx.accounts.proposer.key();
+        proposal_account.proposal_type = ProposalType::ConfigUpdate as u8;
+        proposal_account.description = description;
+        proposal_account.votes_for = 0;
+        proposal_account.votes_against = 0;
+        proposal_account.votes_abstain = 0;
+        proposal_account.dao_org = dao_org;
+        proposal_account.status = ProposalStatus::Draft as u8;
+        proposal_account.created_at = Clock::get()?.unix_timestamp;
+        proposal_account.end_slot = Clock::get()?.slot + dao_config.voting_period_slots;
+        proposal_account.executed_at = None;
+        proposal_account.instruction_count = 0;
+        proposal_account.instructions_executed = 0;
+        proposal_account.eta = 0;
+
+        config_proposal.version = ACCOUNT_VERSION;
+        config_proposal.proposal_id = dao_config.proposal_count;
+        config_proposal.proposed_distribution_interval = proposed_distribution_interval;
+        config_proposal.proposed_max_rewards_per_user = proposed_max_rewards_per_user;
+        config_proposal.status = ConfigProposalStatus::Pending;
+        config_proposal.created_at = Clock::get()?.unix_timestamp;
+        
+        msg!("New configuration proposal created: Distribution interval: {}, Max rewards: {}", 
+             proposed_distribution_interval, proposed_max_rewards_per_user);
+        Ok(())
+    }
+
+    /// CRITICAL: Create a DAO proposal for loyalty behavior changes
+    /// This function enforces the rule requirement for loyalty changes
+    pub fn create_loyalty_change_proposal(
+        ctx: Context<CreateLoyaltyChangeProposal>,
+        dao_org: u8,
+        change_type: u8,
+        parameter_name: String,
+        old_value: String,
+        new_value: String,
+        reason: String,
+    ) -> Result<()> {
+        let dao_config = &mut ctx.accounts.dao_config;
+        let proposal_account = &mut ctx.accounts.proposal;
+        let loyalty_proposal = &mut ctx.accounts.loyalty_proposal;
+
+        check_proposal_threshold(ctx.accounts.proposer_rac_account.amount, dao_config)?;
+
+        // Validate change type
+        require!(change_type < 12, CustomError::InvalidChangeType);
+
+        dao_config.proposal_count += 1;
+        proposal_account.version = ACCOUNT_VERSION;
+        proposal_account.proposal_id = dao_config.proposal_count;
+        proposal_account.proposer = ctx.accounts.proposer.key();
+        proposal_account.proposal_type = ProposalType::LoyaltyChange as u8;
+        proposal_account.description = format!(
+            "Loyalty Change: {} - {}: {} -> {}",
+            get_change_type_name(change_type),
+            parameter_name,
+            old_value,
+            new_value
+        );
+        proposal_account.votes_for = 0;
+        proposal_account.votes_against = 0;
+        proposal_account.votes_abstain = 0;
+        proposal_account.dao_org = dao_org;
+        proposal_account.status = ProposalStatus::Draft as u8;
+        proposal_account.created_at = Clock::get()?.unix_timestamp;
+        proposal_account.end_slot = Clock::get()?.slot + dao_config.voting_period_slots;
+        proposal_account.executed_at = None;
+        proposal_account.instruction_count = 0;
+        proposal_account.instructions_executed = 0;
+        proposal_account.eta = 0;
+
+        loyalty_proposal.version = ACCOUNT_VERSION;
+        loyalty_proposal.proposal_id = dao_config.proposal_count;
+        loyalty_proposal.change_type = change_type;
+        loyalty_proposal.parameter_name = parameter_name;
+        loyalty_proposal.old_value = old_value;
+        loyalty_proposal.new_value = new_value;
+        loyalty_proposal.reason = reason;
+        loyalty_proposal.status = ConfigProposalStatus::Pending;
+        loyalty_proposal.created_at = Clock::get()?.unix_timestamp;
+        
+        msg!("New loyalty change proposal created: {} - {}: {} -> {}", 
+             get_change_type_name(change_type), parameter_name, old_value, new_value);
+        Ok(())
+    }
+    
+    /// Allows a user to propose new DAO governance parameters.
+    /// This is the only way to change quorum, voting period, proposal threshold and timelock.
+    pub fn create_dao_config_proposal(
+        ctx: Context<CreateDaoConfigProposal>,
+        dao_org: u8,
+        quorum_bps: u16,
+        voting_period_slots: u64,
+        min_proposal_threshold: u64,
+        execution_delay: i64,
+        grace_period: i64,
+        description: String,
+    ) -> Result<()> {
+        let dao_config = &mut ctx.accounts.dao_config;
+        let proposal_account = &mut ctx.accounts.proposal;
+        let dao_config_proposal = &mut ctx.accounts.dao_config_proposal;
+
+        check_proposal_threshold(ctx.accounts.proposer_rac_account.amount, dao_config)?;
+        validate_governance_params(quorum_bps, voting_period_slots, execution_delay, grace_period)?;
+
+        dao_config.proposal_count += 1;
+        proposal_account.version = ACCOUNT_VERSION;
+        proposal_account.proposal_id = dao_config.proposal_count;
+        proposal_account.proposer = ctx.accounts.proposer.key();
+        proposal_account.proposal_type = ProposalType::DaoConfigUpdate as u8;
+        proposal_account.description = description;
+        proposal_account.votes_for = 0;
+        proposal_account.votes_against = 0;
+        proposal_account.votes_abstain = 0;
+        proposal_account.dao_org = dao_org;
+        proposal_account.status = ProposalStatus::Draft as u8;
+        proposal_account.created_at = Clock::get()?.unix_timestamp;
+        proposal_account.end_slot = Clock::get()?.slot + dao_config.voting_period_slots;
+        proposal_account.executed_at = None;
+        proposal_account.instruction_count = 0;
+        proposal_account.instructions_executed = 0;
+        proposal_account.eta = 0;
+
+        dao_config_proposal.version = ACCOUNT_VERSION;
+        dao_config_proposal.proposal_id = dao_config.proposal_count;
+        dao_config_proposal.quorum_bps = quorum_bps;
+        dao_config_proposal.voting_period_slots = voting_period_slots;
+        dao_config_proposal.min_proposal_threshold = min_proposal_threshold;
+        dao_config_proposal.execution_delay = execution_delay;
+        dao_config_proposal.grace_period = grace_period;
+        dao_config_proposal.status = ConfigProposalStatus::Pending;
+        dao_config_proposal.created_at = Clock::get()?.unix_timestamp;
+        dao_config_proposal.implemented_at = None;
+
+        msg!("New DAO config proposal created: quorum {} bps, voting period {} slots, threshold {}", 
+             quorum_bps, voting_period_slots, min_proposal_threshold);
+        Ok(())
+    }
+
+    /// Applies an approved DAO config proposal once its timelock has passed.
+    pub fn execute_dao_config_proposal(ctx: Context<ExecuteDaoConfigProposal>, proposal_id: u64) -> Result<()> {
+        let proposal_account = &ctx.accounts.proposal;
+        let current_timestamp = Clock::get()?.unix_timestamp;
+
+        require!(proposal_account.proposal_id == proposal_id, CustomError::InvalidProposalId);
+        require!(proposal_account.proposal_type == ProposalType::DaoConfigUpdate as u8, CustomError::InvalidProposalType);
+        require!(proposal_account.status == ProposalStatus::Approved as u8, CustomError::ProposalNotApproved);
+        check_timelock(proposal_account, &ctx.accounts.dao_config, current_timestamp)?;
+
+        let dao_config_proposal = &mut ctx.accounts.dao_config_proposal;
+        let dao_config = &mut ctx.accounts.dao_config;
+        dao_config.quorum_bps = dao_config_proposal.quorum_bps;
+        dao_config.voting_period_slots = dao_config_proposal.voting_period_slots;
+        dao_config.min_proposal_threshold = dao_config_proposal.min_proposal_threshold;
+        dao_config.execution_delay = dao_config_proposal.execution_delay;
+        dao_config.grace_period = dao_config_proposal.grace_period;
+
+        dao_config_proposal.status = ConfigProposalStatus::Implemented;
+        dao_config_proposal.implemented_at = Some(current_timestamp);
+
+        let proposal_account = &mut ctx.accounts.proposal;
+        proposal_account.status = ProposalStatus::Executed as u8;
+        proposal_account.executed_at = Some(current_timestamp);
+
+        emit!(ProposalExecuted {
+            proposal_id,
+            executed_at: current_timestamp,
+        });
+
+        msg!("DAO config proposal {} executed successfully", proposal_id);
+        Ok(())
+    }
+
+    /// Locks $RAC in the owner's vote-escrow vault for `lock_duration` seconds
+    /// (one week to four years). Voting power decays linearly until the lock expires.
+    pub fn lock_rac(ctx: Context<LockRac>, amount: u64, lock_duration: i64) -> Result<()> {
+        let ve_lock = &mut ctx.accounts.ve_lock;
+        let current_timestamp = Clock::get()?.unix_timestamp;
+
+        require!(amount > 0, CustomError::ZeroDepositAmount);
+        require!(ve_lock.amount == 0, CustomError::LockAlreadyExists);
+        require!(
+            lock_duration >= MIN_LOCK_DURATION && lock_duration <= MAX_LOCK_DURATION,
+            CustomError::InvalidLockDuration
+        );
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.owner_token_account.to_account_info(),
+            to: ctx.accounts.ve_vault.to_account_info(),
+            authority: ctx.accounts.owner.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        token::transfer(cpi_ctx, amount)?;
+
+        ve_lock.version = ACCOUNT_VERSION;
+        ve_lock.owner = ctx.accounts.owner.key();
+        ve_lock.amount = amount;
+        ve_lock.lock_start = current_timestamp;
+        ve_lock.lock_end = current_timestamp + lock_duration;
+        ve_lock.bump = ctx.bumps.ve_lock;
+
+        msg!("Locked {} $RAC until {}. Voting power: {}", 
+             amount, ve_lock.lock_end, ve_voting_power(ve_lock.amount, ve_lock.lock_end, current_timestamp));
+        Ok(())
+    }
+
+    /// Adds $RAC to an existing, unexpired lock without changing its end time.
+    pub fn increase_lock_amount(ctx: Context<IncreaseLockAmount>, amount: u64) -> Result<()> {
+        let ve_lock = &mut ctx.accounts.ve_lock;
+        let current_timestamp = Clock::get()?.unix_timestamp;
+
+        require!(amount > 0, CustomError::ZeroDepositAmount);
+        require!(ve_lock.amount > 0, CustomError::NoActiveLock);
+        require!(ve_lock.lock_end > current_timestamp, CustomError::LockExpired);
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.owner_token_account.to_account_info(),
+            to: ctx.accounts.ve_vault.to_account_info(),
+            authority: ctx.accounts.owner.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        token::transfer(cpi_ctx, amount)?;
+
+        ve_lock.amount += amount;
+
+        msg!("Increased lock by {} $RAC. Total locked: {}", amount, ve_lock.amount);
+        Ok(())
+    }
+
+    /// Pushes the lock end out to `now + lock_duration`. The new end must be later than the current one.
+    pub fn extend_lock(ctx: Context<ExtendLock>, lock_duration: i64) -> Result<()> {
+        let ve_lock = &mut ctx.accounts.ve_lock;
+        let current_timestamp = Clock::get()?.unix_timestamp;
+
+        require!(ve_lock.amount > 0, CustomError::NoActiveLock);
+        require!(ve_lock.lock_end > current_timestamp, CustomError::LockExpired);
+        require!(
+            lock_duration >= MIN_LOCK_DURATION && lock_duration <= MAX_LOCK_DURATION,
+            CustomError::InvalidLockDuration
+        );
+
+        let new_lock_end = current_timestamp + lock_duration;
+        require!(new_lock_end > ve_lock.lock_end, CustomError::LockEndNotExtended);
+        ve_lock.lock_end = new_lock_end;
+
+        msg!("Lock extended until {}. Voting power: {}", 
+             new_lock_end, ve_voting_power(ve_lock.amount, ve_lock.lock_end, current_timestamp));
+        Ok(())
+    }
+
+    /// Returns all locked $RAC once the lock has expired and every proposal
+    /// the owner voted on has closed.
+    pub fn withdraw_lock(ctx: Context<WithdrawLock>) -> Result<()> {
+        let ve_lock = &ctx.accounts.ve_lock;
+        let clock = Clock::get()?;
+
+        require!(ve_lock.amount > 0, CustomError::NoActiveLock);
+        require!(clock.unix_timestamp >= ve_lock.lock_end, CustomError::LockNotExpired);
+        require!(clock.slot >= ve_lock.locked_until_slot, CustomError::TokensLockedForVoting);
+
+        let amount = ve_lock.amount;
+        let owner_key = ctx.accounts.owner.key();
+        let seeds = &[b"ve_lock".as_ref(), owner_key.as_ref(), &[ve_lock.bump]];
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.ve_vault.to_account_info(),
+            to: ctx.accounts.owner_token_account.to_account_info(),
+            authority: ctx.accounts.ve_lock.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, amount)?;
+
+        let ve_lock = &mut ctx.accounts.ve_lock;
+        ve_lock.amount = 0;
+        ve_lock.lock_start = 0;
+        ve_lock.lock_end = 0;
+
+        msg!("Withdrew {} $RAC from expired lock", amount);
+        Ok(())
+    }
+
+    /// Allows a user to vote on a proposal using their veRAC voting power as weight.
+    /// Votes are only accepted while the proposal is active and before `end_slot`.
+    /// `LoyaltyChange` proposals are only open to card holders: pass `loyalty_card`, or
+    /// `card_proof` with the card tree accounts and the proof nodes first in the remaining accounts.
+    /// Delegates then pass `[delegation, delegator_ve_lock]` pairs as remaining accounts
+    /// to add the weight delegated to them.
+    pub fn vote<'info>(
+        ctx: Context<'_, '_, '_, 'info, Vote<'info>>,
+        proposal_id: u64,
+        vote_type: u8,
+        card_proof: Option<CompressedCardProof>,
+    ) -> Result<()> {
+        let voter_key = ctx.accounts.voter.key();
+        let clock = Clock::get()?;
+
+        let mut delegation_accounts = ctx.remaining_accounts;
+        if ctx.accounts.proposal.proposal_type == ProposalType::LoyaltyChange as u8 {
+            let merkle_tree = ctx.accounts.merkle_tree.as_ref().map(|tree| tree.to_account_info());
+            let compression_program = ctx.accounts.compression_program.as_ref().map(|program| program.to_account_info());
+            let (_, rest) = verify_card_holder(
+                voter_key,
+                ctx.accounts.loyalty_card.as_deref(),
+                ctx.accounts.card_tree.as_deref(),
+                merkle_tree.as_ref(),
+                compression_program.as_ref(),
+                card_proof.as_ref(),
+                ctx.remaining_accounts,
+            )?;
+            delegation_accounts = rest;
+        }
+
+        let proposal_account = &mut ctx.accounts.proposal;
+        let voter_record = &mut ctx.accounts.voter_record;
+
+        let choice = VoteChoice::from_u8(vote_type)?;
+        require!(proposal_account.status == ProposalStatus::Active as u8, CustomError::ProposalNotActive);
+        require!(clock.slot < proposal_account.end_slot, CustomError::VotingPeriodEnded);
+
+        // A delegator whose delegation covers this DAO org votes through their delegate only
+        let voter_delegation = &ctx.accounts.voter_delegation;
+        if !voter_delegation.data_is_empty() {
+            let delegation = Delegation::try_deserialize(&mut &voter_delegation.try_borrow_data()?[..])?;
+            require!(!delegation.applies_to(proposal_account.dao_org), CustomError::DelegatorCannotVote);
+        }
+
+        // Weight comes from escrowed tokens, which stay locked until the vote closes,
+        // so the same $RAC cannot be moved to another wallet and counted twice.
+        let mut own_weight = 0;
+        if let Some(ve_lock) = ctx.accounts.ve_lock.as_mut() {
+            own_weight = ve_voting_power(ve_lock.amount, ve_lock.lock_end, clock.unix_timestamp);
+            if own_weight > 0 && proposal_account.end_slot > ve_lock.locked_until_slot {
+                ve_lock.locked_until_slot = proposal_account.end_slot;
+            }
+        }
+
+        let delegated_weight = apply_delegated_weight(
+            delegation_accounts,
+            &voter_key,
+            proposal_account.dao_org,
+            proposal_account.end_slot,
+            clock.unix_timestamp,
+        )?;
+
+        let voter_token_balance = own_weight + delegated_weight;
+        require!(voter_token_balance > 0, CustomError::InsufficientBalanceForVote);
+
+        voter_record.version = ACCOUNT_VERSION;
+        voter_record.voter = voter_key;
+        voter_record.proposal_id = proposal_id;
+        voter_record.choice = choice.clone();
+        voter_record.weight = voter_token_balance;
+        voter_record.delegated_weight = delegated_weight;
+        
+        match choice {
+            VoteChoice::For => {
+                proposal_account.votes_for += voter_token_balance;
+                msg!("Voted FOR proposal {} with a weight of {}", proposal_id, voter_token_balance);
+            }
+            VoteChoice::Against => {
+                proposal_account.votes_against += voter_token_balance;
+                msg!("Voted AGAINST proposal {} with a weight of {}", proposal_id, voter_token_balance);
+            }
+            VoteChoice::Abstain => {
+                proposal_account.votes_abstain += voter_token_balance;
+                msg!("ABSTAINED on proposal {} with a weight of {}", proposal_id, voter_token_balance);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Delegates the owner's veRAC voting power to `delegate` without moving any $RAC.
+    /// `dao_org` scopes the delegation to one DAO org; `None` covers all of them.
+    pub fn delegate(ctx: Context<DelegateVotes>, delegate: Pubkey, dao_org: Option<u8>) -> Result<()> {
+        let delegation = &mut ctx.accounts.delegation;
+        let delegator = ctx.accounts.delegator.key();
+
+        require!(delegate != delegator, CustomError::CannotDelegateToSelf);
+        if let Some(org) = dao_org {
+            require!(org < DAO_ORG_COUNT, CustomError::InvalidDaoOrg);
+        }
+        // Votes already cast with this lock must close first, otherwise the same
+        // power could be counted again by the delegate.
+        require!(Clock::get()?.slot >= ctx.accounts.ve_lock.locked_until_slot, CustomError::TokensLockedForVoting);
+
+        delegation.version = ACCOUNT_VERSION;
+        delegation.delegator = delegator;
+        delegation.delegate = delegate;
+        delegation.dao_org = dao_org;
+        delegation.created_at = Clock::get()?.unix_timestamp;
+        delegation.bump = ctx.bumps.delegation;
+
+        msg!("Voting power of {} delegated to {}", delegator, delegate);
+        Ok(())
+    }
+
+    /// Revokes a delegation once every proposal voted on with the delegated power has closed.
+    pub fn undelegate(ctx: Context<Undelegate>) -> Result<()> {
+        require!(Clock::get()?.slot >= ctx.accounts.ve_lock.locked_until_slot, CustomError::TokensLockedForVoting);
+
+        msg!("Delegation from {} to {} revoked", ctx.accounts.delegation.delegator, ctx.accounts.delegation.delegate);
+        Ok(())
+    }
+
+    /// Allows DAO to execute approved configuration proposals.
+    pub fn execute_config_proposal(ctx: Context<ExecuteConfigProposal>, proposal_id: u64) -> Result<()> {
+        let proposal_account = &ctx.accounts.proposal;
+        let config_proposal = &mut ctx.accounts.config_proposal;
+        let rewards_config = &mut ctx.accounts.rewards_config;
+        let current_timestamp = Clock::get()?.unix_timestamp;
+
+        require!(proposal_account.proposal_id == proposal_id, CustomError::InvalidProposalId);
+        require!(proposal_account.proposal_type == ProposalType::ConfigUpdate as u8, CustomError::InvalidProposalType);
+        require!(proposal_account.status == ProposalStatus::Approved as u8, CustomError::ProposalNotApproved);
+        check_timelock(proposal_account, &ctx.accounts.dao_config, current_timestamp)?;
+
+        // Check if proposal has enough votes to pass (simple majority)
+        let total_votes = proposal_account.votes_for + proposal_account.votes_against;
+        require!(total_votes > 0, CustomError::NoVotesCast);
+        require!(proposal_account.votes_for > proposal_account.votes_against, CustomError::ProposalNotPassed);
+
+        // Execute the configuration changes
+        rewards_config.distribution_interval = config_proposal.proposed_distribution_interval;
+        rewards_config.max_rewards_per_user = config_proposal.proposed_max_rewards_per_user;
+        rewards_config.updated_at = Clock::get()?.unix_timestamp;
+
+        // Mark proposal as implemented
+        config_proposal.status = ConfigProposalStatus::Implemented;
+        config_proposal.implemented_at = Some(Clock::get()?.unix_timestamp);
+
+        let proposal_account = &mut ctx.accounts.proposal;
+        proposal_account.status = ProposalStatus::Executed as u8;
+        proposal_account.executed_at = Some(current_timestamp);
+
+        emit!(ProposalExecuted {
+            proposal_id,
+            executed_at: current_timestamp,
+        });
+
+        msg!("Configuration proposal {} executed successfully", proposal_id);
+        Ok(())
+    }
+
+    /// Opens voting on a draft proposal. The voting window starts at activation.
+    /// Treasury and Governance proposals only reach `Executed` through their stored
+    /// instructions, so they need at least one before voting opens.
+    pub fn activate_proposal(ctx: Context<ActivateProposal>, proposal_id: u64) -> Result<()> {
+        let dao_config = &ctx.accounts.dao_config;
+        let proposal_account = &mut ctx.accounts.proposal;
+
+        require!(proposal_account.proposal_id == proposal_id, CustomError::InvalidProposalId);
+        require!(proposal_account.status == ProposalStatus::Draft as u8, CustomError::ProposalNotDraft);
+        if proposal_account.proposal_type == ProposalType::Treasury as u8
+            || proposal_account.proposal_type == ProposalType::Governance as u8
+        {
+            require!(proposal_account.instruction_count > 0, CustomError::ProposalHasNoInstructions);
+        }
+
+        proposal_account.status = ProposalStatus::Active as u8;
+        proposal_account.end_slot = Clock::get()?.slot + dao_config.voting_period_slots;
+
+        msg!("Proposal {} activated. Voting ends at slot {}", proposal_id, proposal_account.end_slot);
+        Ok(())
+    }
+
+    /// Closes voting once `end_slot` has passed and records the outcome.
+    /// Anyone can call this; the result depends only on the on-chain tally.
+    pub fn finalize_proposal(ctx: Context<FinalizeProposal>, proposal_id: u64) -> Result<()> {
+        let dao_config = &ctx.accounts.dao_config;
+        let proposal_account = &mut ctx.accounts.proposal;
+
+        require!(proposal_account.proposal_id == proposal_id, CustomError::InvalidProposalId);
+        require!(proposal_account.status == ProposalStatus::Active as u8, CustomError::ProposalNotActive);
+        require!(Clock::get()?.slot >= proposal_account.end_slot, CustomError::VotingStillActive);
+
+        let total_votes = proposal_account.votes_for + proposal_account.votes_against + proposal_account.votes_abstain;
+        let quorum_met = is_quorum_met(total_votes, dao_config.minted_supply, dao_config.quorum_bps);
+
+        if quorum_met && proposal_account.votes_for > proposal_account.votes_against {
+            // Approved proposals are queued behind the timelock before they can execute
+            proposal_account.status = ProposalStatus::Approved as u8;
+            proposal_account.eta = Clock::get()?.unix_timestamp + dao_config.execution_delay;
+
+            emit!(ProposalQueued {
+                proposal_id,
+                eta: proposal_account.eta,
+            });
+
+            msg!("Proposal {} approved: {} for, {} against. Executable from {}", 
+                 proposal_id, proposal_account.votes_for, proposal_account.votes_against, proposal_account.eta);
+        } else {
+            proposal_account.status = ProposalStatus::Rejected as u8;
+            msg!("Proposal {} rejected: {} for, {} against, quorum met: {}", 
+                 proposal_id, proposal_account.votes_for, proposal_account.votes_against, quorum_met);
+        }
+
+        Ok(())
+    }
+
+    /// Allows the proposer to withdraw a proposal that has not been finalized yet.
+    pub fn cancel_proposal(ctx: Context<CancelProposal>, proposal_id: u64) -> Result<()> {
+        let proposal_account = &mut ctx.accounts.proposal;
+
+        require!(proposal_account.proposal_id == proposal_id, CustomError::InvalidProposalId);
+        require!(
+            proposal_account.status == ProposalStatus::Draft as u8
+                || proposal_account.status == ProposalStatus::Active as u8,
+            CustomError::ProposalCannotBeCancelled
+        );
+
+        proposal_account.status = ProposalStatus::Cancelled as u8;
+
+        msg!("Proposal {} cancelled by proposer", proposal_id);
+        Ok(())
+    }
+
+    /// Attaches an instruction to a draft Treasury or Governance proposal.
+    /// Instructions are stored in order and executed by the DAO authority PDA once approved.
+    pub fn insert_proposal_instruction(
+        ctx: Context<InsertProposalInstruction>,
+        proposal_id: u64,
+        index: u16,
+        program_id: Pubkey,
+        accounts: Vec<ProposalAccountMeta>,
+        data: Vec<u8>,
+    ) -> Result<()> {
+        let proposal_account = &mut ctx.accounts.proposal;
+        let proposal_instruction = &mut ctx.accounts.proposal_instruction;
+
+        require!(proposal_account.proposal_id == proposal_id, CustomError::InvalidProposalId);
+        require!(
+            proposal_account.proposal_type == ProposalType::Treasury as u8
+                || proposal_account.proposal_type == ProposalType::Governance as u8,
+            CustomError::InvalidProposalType
+        );
+        require!(proposal_account.status == ProposalStatus::Draft as u8, CustomError::ProposalNotDraft);
+        require!(index == proposal_account.instruction_count, CustomError::InvalidInstructionIndex);
+        require!(accounts.len() <= MAX_INSTRUCTION_ACCOUNTS, CustomError::InstructionTooLarge);
+        require!(data.len() <= MAX_INSTRUCTION_DATA_LEN, CustomError::InstructionTooLarge);
+        check_proposal_instruction(proposal_account.proposal_type, &program_id, &accounts, &data)?;
+
+        proposal_instruction.version = ACCOUNT_VERSION;
+        proposal_instruction.proposal_id = proposal_id;
+        proposal_instruction.index = index;
+        proposal_instruction.program_id = program_id;
+        proposal_instruction.accounts = accounts;
+        proposal_instruction.data = data;
+        proposal_instruction.executed_at = None;
+
+        proposal_account.instruction_count += 1;
+
+        msg!("Instruction {} for program {} added to proposal {}", index, program_id, proposal_id);
+        Ok(())
+    }
+
+    /// Executes the next stored instruction of an approved proposal via CPI,
+    /// signed by the DAO authority PDA. The accounts the instruction references,
+    /// including the target program, are passed as remaining accounts.
+    pub fn execute_proposal_instruction(ctx: Context<ExecuteProposalInstruction>, proposal_id: u64, index: u16) -> Result<()> {
+        let proposal_account = &ctx.accounts.proposal;
+        let proposal_instruction = &ctx.accounts.proposal_instruction;
+
+        require!(proposal_account.proposal_id == proposal_id, CustomError::InvalidProposalId);
+        require!(proposal_account.status == ProposalStatus::Approved as u8, CustomError::ProposalNotApproved);
+        require!(index == proposal_account.instructions_executed, CustomError::InvalidInstructionIndex);
+        require!(proposal_instruction.executed_at.is_none(), CustomError::InstructionAlreadyExecuted);
+        check_timelock(proposal_account, &ctx.accounts.dao_config, Clock::get()?.unix_timestamp)?;
+
+        let instruction = Instruction {
+            program_id: proposal_instruction.program_id,
+            accounts: proposal_instruction
+                .accounts
+                .iter()
+                .map(|meta| AccountMeta {
+                    pubkey: meta.pubkey,
+                    is_signer: meta.is_signer,
+                    is_writable: meta.is_writable,
+                })
+                .collect(),
+            data: proposal_instruction.data.clone(),
+        };
+
+        let mut account_infos = ctx.remaining_accounts.to_vec();
+        account_infos.push(ctx.accounts.dao_authority.to_account_info());
+
+        let seeds = &[b"dao_authority".as_ref(), &[ctx.bumps.dao_authority]];
+        let signer = &[&seeds[..]];
+        invoke_signed(&instruction, &account_infos, signer)?;
+
+        let current_timestamp = Clock::get()?.unix_timestamp;
+        ctx.accounts.proposal_instruction.executed_at = Some(current_timestamp);
+
+        let proposal_account = &mut ctx.accounts.proposal;
+        proposal_account.instructions_executed += 1;
+        if proposal_account.instructions_executed == proposal_account.instruction_count {
+            proposal_account.status = ProposalStatus::Executed as u8;
+            proposal_account.executed_at = Some(current_timestamp);
+
+            emit!(ProposalExecuted {
+                proposal_id,
+                executed_at: current_timestamp,
+            });
+        }
+
+        msg!("Executed instruction {} of proposal {}", index, proposal_id);
+        Ok(())
+    }
+
+    /// Lets the guardian block a queued proposal before its timelock expires.
+    pub fn veto_proposal(ctx: Context<VetoProposal>, proposal_id: u64) -> Result<()> {
+        let proposal_account = &mut ctx.accounts.proposal;
+        let current_timestamp = Clock::get()?.unix_timestamp;
+
+        require!(proposal_account.proposal_id == proposal_id, CustomError::InvalidProposalId);
+        require!(proposal_account.status == ProposalStatus::Approved as u8, CustomError::ProposalNotApproved);
+        require!(current_timestamp < proposal_account.eta, CustomError::TimelockExpired);
+
+        proposal_account.status = ProposalStatus::Vetoed as u8;
+
+        emit!(ProposalVetoed {
+            proposal_id,
+            guardian: ctx.accounts.guardian.key(),
+            vetoed_at: current_timestamp,
+        });
+
+        msg!("Proposal {} vetoed by guardian", proposal_id);
+        Ok(())
+    }
+}
+
+// Helper function to check which proposal types may carry an instruction.
+// Minting and moving DAO funds are treasury decisions. Outside this program the DAO authority
+// only signs program upgrades: signed SPL Token calls, or calls into other programs that could
+// forward the signature, would mint $RAC past the cap or take the mint authority away.
+fn check_proposal_instruction(
+    proposal_type: u8,
+    program_id: &Pubkey,
+    accounts: &[ProposalAccountMeta],
+    data: &[u8],
+) -> Result<()> {
+    if *program_id == crate::ID {
+        if is_treasury_instruction(data) {
+            require!(proposal_type == ProposalType::Treasury as u8, CustomError::InvalidProposalType);
+        }
+        return Ok(());
+    }
+
+    let (dao_authority, _) = Pubkey::find_program_address(&[b"dao_authority"], &crate::ID);
+    let signs_as_dao = accounts.iter().any(|meta| meta.pubkey == dao_authority && meta.is_signer);
+    if signs_as_dao {
+        require!(*program_id == bpf_loader_upgradeable::ID, CustomError::ProgramNotAllowed);
+        require!(proposal_type == ProposalType::Governance as u8, CustomError::InvalidProposalType);
+    }
+    Ok(())
+}
+
+// Helper function to detect instructions of this program that move DAO funds
+fn is_treasury_instruction(data: &[u8]) -> bool {
+    [
+        crate::instruction::MintRac::DISCRIMINATOR.as_ref(),
+        crate::instruction::WithdrawFromTreasury::DISCRIMINATOR.as_ref(),
+        crate::instruction::CreateVestingSchedule::DISCRIMINATOR.as_ref(),
+        crate::instruction::RevokeVesting::DISCRIMINATOR.as_ref(),
+    ]
+    .iter()
+    .any(|discriminator| data.starts_with(discriminator))
+}
+
+// Helper function to create a program-owned PDA the way Anchor's `init` does. An address that
+// already holds lamports is topped up, allocated and assigned instead, so pre-funding it
+// cannot block creation.
+fn create_pda_account<'info>(
+    account: &AccountInfo<'info>,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    space: usize,
+    signer: &[&[&[u8]]],
+) -> Result<()> {
+    let required = Rent::get()?.minimum_balance(space);
+    let current = account.lamports();
+
+    if current == 0 {
+        let cpi_accounts = system_program::CreateAccount {
+            from: payer.to_account_info(),
+            to: account.clone(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(system_program.to_account_info(), cpi_accounts, signer);
+        return system_program::create_account(cpi_ctx, required, space as u64, &crate::ID);
+    }
+
+    let shortfall = required.saturating_sub(current);
+    if shortfall > 0 {
+        let cpi_accounts = system_program::Transfer {
+            from: payer.to_account_info(),
+            to: account.clone(),
+        };
+        let cpi_ctx = CpiContext::new(system_program.to_account_info(), cpi_accounts);
+        system_program::transfer(cpi_ctx, shortfall)?;
+    }
+
+    let cpi_accounts = system_program::Allocate {
+        account_to_allocate: account.clone(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(system_program.to_account_info(), cpi_accounts, signer);
+    system_program::allocate(cpi_ctx, space as u64)?;
+
+    let cpi_accounts = system_program::Assign {
+        account_to_assign: account.clone(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(system_program.to_account_info(), cpi_accounts, signer);
+    system_program::assign(cpi_ctx, &crate::ID)
+}
+
+/// Accounts needed to mint a one-of-one loyalty card and verify it into its collection.
+struct CardMintAccounts<'info> {
+    card_mint: AccountInfo<'info>,
+    owner_card_account: AccountInfo<'info>,
+    card_metadata: AccountInfo<'info>,
+    card_master_edition: AccountInfo<'info>,
+    collection_mint: AccountInfo<'info>,
+    collection_metadata: AccountInfo<'info>,
+    collection_master_edition: AccountInfo<'info>,
+    card_authority: AccountInfo<'info>,
+    payer: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    token_metadata_program: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    rent: AccountInfo<'info>,
+}
+
+// Helper function to grow a program-owned account to `new_len`, topping up rent from `payer`
+fn grow_account<'info>(
+    account: &AccountInfo<'info>,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    new_len: usize,
+) -> Result<()> {
+    let required = Rent::get()?.minimum_balance(new_len);
+    let shortfall = required.saturating_sub(account.lamports());
+    if shortfall > 0 {
+        let cpi_accounts = system_program::Transfer {
+            from: payer.to_account_info(),
+            to: account.clone(),
+        };
+        let cpi_ctx = CpiContext::new(system_program.to_account_info(), cpi_accounts);
+        system_program::transfer(cpi_ctx, shortfall)?;
+    }
+    account.realloc(new_len, false)?;
+    Ok(())
+}
+
+// Helper function to resize an account and overwrite it with its migrated contents
+fn write_migrated_account<'info, T: AccountSerialize>(
+    account: &AccountInfo<'info>,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    new_len: usize,
+    migrated: &T,
+) -> Result<()> {
+    grow_account(account, payer, system_program, new_len)?;
+    let mut data = account.try_borrow_mut_data()?;
+    data.fill(0);
+    let mut writer: &mut [u8] = &mut data[..];
+    migrated.try_serialize(&mut writer)?;
+    Ok(())
+}
+
+// Helper function to record a fractional pool and mint its full supply into the vault.
+// The mint authority is revoked afterwards so the supply can never grow.
+fn mint_fractional_supply<'info>(
+    fractional_nft: &mut FractionalNft,
+    nft: Pubkey,
+    fractional_mint: &Account<'info, Mint>,
+    fractional_vault: &Account<'info, TokenAccount>,
+    admin: &Signer<'info>,
+    token_program: &Program<'info, Token>,
+    supply: u64,
+) -> Result<()> {
+    require!(supply > 0, CustomError::InvalidFractionalSupply);
+
+    fractional_nft.version = ACCOUNT_VERSION;
+    fractional_nft.parent_nft = nft;
+    fractional_nft.mint = fractional_mint.key();
+    fractional_nft.total_supply = supply;
+    fractional_nft.supply_cap = supply;
+    fractional_nft.sold = 0;
+
+    let cpi_accounts = MintTo {
+        mint: fractional_mint.to_account_info(),
+        to: fractional_vault.to_account_info(),
+        authority: admin.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new(token_program.to_account_info(), cpi_accounts);
+    token::mint_to(cpi_ctx, supply)?;
+
+    let cpi_accounts = SetAuthority {
+        current_authority: admin.to_account_info(),
+        account_or_mint: fractional_mint.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new(token_program.to_account_info(), cpi_accounts);
+    token::set_authority(cpi_ctx, AuthorityType::MintTokens, None)?;
+    Ok(())
+}
+
+// Helper function to check NFT definition strings fit the `NftAccount` layout and Metaplex limits
+fn validate_nft_fields(nft_name: &str, display_name: &str, symbol: &str, uri: &str) -> Result<()> {
+    require!(!nft_name.is_empty() && nft_name.len() <= MAX_METADATA_NAME_LEN, CustomError::InvalidNftName);
+    require!(
+        !display_name.is_empty() && display_name.len() <= MAX_METADATA_NAME_LEN,
+        CustomError::InvalidDisplayName
+    );
+    require!(symbol.len() <= MAX_METADATA_SYMBOL_LEN, CustomError::SymbolTooLong);
+    require!(uri.len() <= MAX_METADATA_URI_LEN, CustomError::UriTooLong);
+    Ok(())
+}
+
+// Helper function to check an NFT's address against its seeds. NFTs created before the custody
+// type joined the seeds keep their original `[b"nft", nft_name, symbol]` address.
+fn is_nft_address(address: &Pubkey, custody_type: CustodyType, nft_name: &str, symbol: &str) -> bool {
+    let (current, _) = Pubkey::find_program_address(
+        &[b"nft", &[custody_type as u8], nft_name.as_bytes(), symbol.as_bytes()],
+        &crate::ID,
+    );
+    let (legacy, _) = Pubkey::find_program_address(&[b"nft", nft_name.as_bytes(), symbol.as_bytes()], &crate::ID);
+    *address == current || *address == legacy
+}
+
+// Helper function to build a card's Metaplex metadata from its NFT definition
+fn card_metadata(nft: &NftAccount, collection_mint: Pubkey) -> Result<DataV2> {
+    require!(
+        nft.display_name.len() <= MAX_METADATA_NAME_LEN
+            && nft.symbol.len() <= MAX_METADATA_SYMBOL_LEN
+            && nft.uri.len() <= MAX_METADATA_URI_LEN,
+        CustomError::MetadataFieldTooLong
+    );
+    Ok(DataV2 {
+        name: nft.display_name.clone(),
+        symbol: nft.symbol.clone(),
+        uri: nft.uri.clone(),
+        seller_fee_basis_points: 0,
+        creators: None,
+        collection: Some(MetadataCollection {
+            verified: false,
+            key: collection_mint,
+        }),
+        uses: None,
+    })
+}
+
+// Helper function to mint a card token, create its metadata and master edition,
+// and verify it into the collection, all signed by the card authority PDA
+fn mint_card_nft(accounts: CardMintAccounts<'_>, data: DataV2, card_authority_bump: u8) -> Result<()> {
+    let seeds = &[b"card_authority".as_ref(), &[card_authority_bump]];
+    let signer = &[&seeds[..]];
+
+    let cpi_accounts = MintTo {
+        mint: accounts.card_mint.clone(),
+        to: accounts.owner_card_account.clone(),
+        authority: accounts.card_authority.clone(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(accounts.token_program.clone(), cpi_accounts, signer);
+    token::mint_to(cpi_ctx, 1)?;
+
+    let cpi_accounts = CreateMetadataAccountsV3 {
+        metadata: accounts.card_metadata.clone(),
+        mint: accounts.card_mint.clone(),
+        mint_authority: accounts.card_authority.clone(),
+        payer: accounts.payer.clone(),
+        update_authority: accounts.card_authority.clone(),
+        system_program: accounts.system_program.clone(),
+        rent: accounts.rent.clone(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(accounts.token_metadata_program.clone(), cpi_accounts, signer);
+    token_metadata::create_metadata_accounts_v3(cpi_ctx, data, true, true, None)?;
+
+    // The master edition takes over the mint authority, fixing the supply at one.
+    let cpi_accounts = CreateMasterEditionV3 {
+        edition: accounts.card_master_edition.clone(),
+        mint: accounts.card_mint.clone(),
+        update_authority: accounts.card_authority.clone(),
+        mint_authority: accounts.card_authority.clone(),
+        payer: accounts.payer.clone(),
+        metadata: accounts.card_metadata.clone(),
+        token_program: accounts.token_program.clone(),
+        system_program: accounts.system_program.clone(),
+        rent: accounts.rent.clone(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(accounts.token_metadata_program.clone(), cpi_accounts, signer);
+    token_metadata::create_master_edition_v3(cpi_ctx, Some(0))?;
+
+    let cpi_accounts = SetAndVerifySizedCollectionItem {
+        metadata: accounts.card_metadata,
+        collection_authority: accounts.card_authority.clone(),
+        payer: accounts.payer,
+        update_authority: accounts.card_authority,
+        collection_mint: accounts.collection_mint,
+        collection_metadata: accounts.collection_metadata,
+        collection_master_edition: accounts.collection_master_edition,
+    };
+    let cpi_ctx = CpiContext::new_with_signer(accounts.token_metadata_program, cpi_accounts, signer);
+    token_metadata::set_and_verify_sized_collection_item(cpi_ctx, None)
+}
+
+// Helper function to make a card soulbound: the holder delegates it to the card authority PDA,
+// which then freezes the token account through the card's master edition
+#[allow(clippy::too_many_arguments)]
+fn freeze_card<'info>(
+    card_mint: AccountInfo<'info>,
+    card_account: AccountInfo<'info>,
+    card_master_edition: AccountInfo<'info>,
+    card_authority: AccountInfo<'info>,
+    owner: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    token_metadata_program: AccountInfo<'info>,
+    card_authority_bump: u8,
+) -> Result<()> {
+    let cpi_accounts = Approve {
+        to: card_account.clone(),
+        delegate: card_authority.clone(),
+        authority: owner,
+    };
+    let cpi_ctx = CpiContext::new(token_program.clone(), cpi_accounts);
+    token::approve(cpi_ctx, 1)?;
+
+    let seeds = &[b"card_authority".as_ref(), &[card_authority_bump]];
+    let signer = &[&seeds[..]];
+
+    let cpi_accounts = FreezeDelegatedAccount {
+        metadata: token_metadata_program.clone(),
+        delegate: card_authority,
+        token_account: card_account,
+        edition: card_master_edition,
+        mint: card_mint,
+        token_program,
+    };
+    let cpi_ctx = CpiContext::new_with_signer(token_metadata_program, cpi_accounts, signer);
+    token_metadata::freeze_delegated_account(cpi_ctx)
+}
+
+// Helper function to thaw a frozen card so it can be burned or moved
+fn thaw_card<'info>(
+    card_mint: AccountInfo<'info>,
+    card_account: AccountInfo<'info>,
+    card_master_edition: AccountInfo<'info>,
+    card_authority: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    token_metadata_program: AccountInfo<'info>,
+    card_authority_bump: u8,
+) -> Result<()> {
+    let seeds = &[b"card_authority".as_ref(), &[card_authority_bump]];
+    let signer = &[&seeds[..]];
+
+    let cpi_accounts = ThawDelegatedAccount {
+        metadata: token_metadata_program.clone(),
+        delegate: card_authority,
+        token_account: card_account,
+        edition: card_master_edition,
+        mint: card_mint,
+        token_program,
+    };
+    let cpi_ctx = CpiContext::new_with_signer(token_metadata_program, cpi_accounts, signer);
+    token_metadata::thaw_delegated_account(cpi_ctx)
+}
+
+// Helper function to total what a holder has invested or staked towards evolution, in RAC base units.
+// veRAC locks count until they expire and reward stakes at their staked amount. USDT invested in
+// the card's NFT counts one whole USDT per whole RAC, with fractional shares valued at their buy price.
+#[allow(clippy::too_many_arguments)]
+fn evolution_holdings(
+    owner: &Pubkey,
+    nft: &Account<NftAccount>,
+    ve_lock: Option<&VeLock>,
+    reward_stake: Option<&RewardStake>,
+    custodial_investment: Option<&CustodialInvestment>,
+    fractional_investment: Option<(&NonCustodialInvestment, &FractionalNft)>,
+    usdt_decimals: u8,
+    now: i64,
+) -> Result<u64> {
+    let locked = ve_lock
+        .filter(|lock| lock.owner == *owner && lock.lock_end > now)
+        .map_or(0, |lock| lock.amount);
+    let staked = reward_stake
+        .filter(|stake| stake.owner == *owner && stake.nft == nft.key())
+        .map_or(0, |stake| stake.amount);
+
+    let invested_usdt = if nft.custody_type == CustodyType::Custodial {
+        custodial_investment
+            .filter(|investment| investment.investor == *owner && investment.nft == nft.key())
+            .map_or(0, |investment| investment.amount as u128)
+    } else {
+        match fractional_investment {
+            Some((investment, fractional_nft))
+                if investment.investor == *owner && investment.nft == nft.key() && fractional_nft.parent_nft == nft.key() =>
+            {
+                require!(fractional_nft.supply_cap > 0, CustomError::InvalidFractionalAccount);
+                (investment.amount as u128) * (nft.buy_price_usdt as u128) / (fractional_nft.supply_cap as u128)
+            }
+            _ => 0,
+        }
+    };
+    let invested = usdt_to_reward_units(invested_usdt, usdt_decimals, RAC_DECIMALS)?;
+
+    Ok(locked.saturating_add(staked).saturating_add(invested))
+}
+
+// Helper function to roll a daily reward window over to `today` and return what is left of `cap`.
+fn daily_reward_remaining(day: &mut i64, rewarded_today: &mut u64, cap: u64, today: i64) -> u64 {
+    if *day != today {
+        *day = today;
+        *rewarded_today = 0;
+    }
+    cap.saturating_sub(*rewarded_today)
+}
+
+// Helper function to evaluate a card against its NFT's evolution requirements.
+// `evolution_min_investment` is in USDT base units; it is converted to RAC base units to match `invested_amount`.
+fn evolution_eligibility(
+    card: &LoyaltyCard,
+    nft: &NftAccount,
+    invested_amount: u64,
+    usdt_decimals: u8,
+) -> Result<EvolutionEligibility> {
+    let is_active = card.status == CardStatus::Active as u8;
+    let is_evolvable = nft.is_evolvable;
+    let already_evolved = card.evolved;
+    let required_amount = usdt_to_reward_units(nft.evolution_min_investment as u128, usdt_decimals, RAC_DECIMALS)?;
+
+    Ok(EvolutionEligibility {
+        card: card.mint,
+        is_active,
+        is_evolvable,
+        already_evolved,
+        invested_amount,
+        required_amount,
+        eligible: is_active && is_evolvable && !already_evolved && invested_amount >= required_amount,
+    })
+}
+
+// Helper function to value the upgrade bonus in RAC base units.
+// The bonus is `upgrade_bonus_ratio` bps of the USDT paid, converted one whole USDT to one whole RAC.
+fn upgrade_bonus_amount(price_difference: u64, upgrade_bonus_ratio: u64, usdt_decimals: u8) -> Result<u64> {
+    let bonus_usdt = (price_difference as u128) * (upgrade_bonus_ratio as u128) / 10_000;
+    usdt_to_reward_units(bonus_usdt, usdt_decimals, RAC_DECIMALS)
+}
+
+// Helper function to derive the PDA that owns and delegates a holder's compressed cards
+fn card_custody_address(holder: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"card_custody", holder.as_ref()], &crate::ID).0
+}
+
+// Helper function to check a compressed card leaf against the tree root.
+// The leaf's owner and delegate are both the holder's `card_custody` PDA.
+fn verify_compressed_holder<'info>(
+    compression_program: &AccountInfo<'info>,
+    merkle_tree: &AccountInfo<'info>,
+    proof: &[AccountInfo<'info>],
+    holder: Pubkey,
+    card_proof: &CompressedCardProof,
+) -> Result<()> {
+    let custody = card_custody_address(&holder);
+    let leaf = LeafSchema::V1 {
+        id: mpl_bubblegum::utils::get_asset_id(merkle_tree.key, card_proof.nonce),
+        owner: custody,
+        delegate: custody,
+        nonce: card_proof.nonce,
+        data_hash: card_proof.data_hash,
+        creator_hash: card_proof.creator_hash,
+    };
+    let index = u32::try_from(card_proof.nonce).map_err(|_| error!(CustomError::InvalidCompressedProof))?;
+
+    let cpi_accounts = spl_account_compression::cpi::accounts::VerifyLeaf {
+        merkle_tree: merkle_tree.clone(),
+    };
+    let cpi_ctx = CpiContext::new(compression_program.clone(), cpi_accounts)
+        .with_remaining_accounts(proof.to_vec());
+    spl_account_compression::cpi::verify_leaf(cpi_ctx, card_proof.root, leaf.hash(), index)
+}
+
+// Helper function to check that `holder` holds an active card, either as a `LoyaltyCard` or as a
+// compressed card proven against its registered tree. The first `proof_len` remaining accounts
+// are the Merkle proof; the NFT of the card and the accounts after the proof are returned.
+#[allow(clippy::too_many_arguments)]
+fn verify_card_holder<'a, 'info>(
+    holder: Pubkey,
+    loyalty_card: Option<&LoyaltyCard>,
+    card_tree: Option<&CardTree>,
+    merkle_tree: Option<&AccountInfo<'info>>,
+    compression_program: Option<&AccountInfo<'info>>,
+    card_proof: Option<&CompressedCardProof>,
+    remaining_accounts: &'a [AccountInfo<'info>],
+) -> Result<(Pubkey, &'a [AccountInfo<'info>])> {
+    if let Some(card) = loyalty_card {
+        require!(card.owner == holder, CustomError::CardHolderRequired);
+        require!(card.status == CardStatus::Active as u8, CustomError::CardNotActive);
+        return Ok((card.nft, remaining_accounts));
+    }
+
+    let (Some(card_proof), Some(card_tree), Some(merkle_tree), Some(compression_program)) =
+        (card_proof, card_tree, merkle_tree, compression_program)
+    else {
+        return err!(CustomError::CardHolderRequired);
+    };
+    require!(card_tree.merkle_tree == merkle_tree.key(), CustomError::InvalidCardTree);
+    let proof_len = card_proof.proof_len as usize;
+    require!(remaining_accounts.len() >= proof_len, CustomError::InvalidCompressedProof);
+    let (proof, rest) = remaining_accounts.split_at(proof_len);
+
+    verify_compressed_holder(compression_program, merkle_tree, proof, holder, card_proof)?;
+    Ok((card_tree.nft, rest))
+}
+
+// Helper function to price fractional shares, rounding up so buyers never underpay
+fn fractional_share_cost(shares: u64, buy_price_usdt: u64, supply: u64) -> Result<u64> {
+    let cost = ((shares as u128) * (buy_price_usdt as u128) + (supply as u128) - 1) / (supply as u128);
+    u64::try_from(cost).map_err(|_| error!(CustomError::PriceExceedsMaximum))
+}
+
+// Helper function to settle an investment and take what can be claimed now.
+// Enforces the distribution interval between claims and the per-claim `max_rewards_per_user` cap.
+fn take_claimable_income(
+    shares: u64,
+    acc_income_per_share: u128,
+    reward_debt: &mut u128,
+    pending_income: &mut u64,
+    last_claim_at: &mut i64,
+    rewards_config: &RewardsConfig,
+    now: i64,
+) -> Result<u64> {
+    require!(rewards_config.is_active, CustomError::RewardsInactive);
+    require!(
+        now - *last_claim_at >= rewards_config.distribution_interval as i64,
+        CustomError::ClaimTooEarly
+    );
+
+    settle_income(shares, acc_income_per_share, reward_debt, pending_income);
+    let payout = (*pending_income).min(rewards_config.max_rewards_per_user);
+    require!(payout > 0, CustomError::NothingToClaim);
+
+    *pending_income -= payout;
+    *last_claim_at = now;
+    Ok(payout)
+}
+
+// Helper function to move income accrued since the last checkpoint into `pending_income`
+fn settle_income(shares: u64, acc_income_per_share: u128, reward_debt: &mut u128, pending_income: &mut u64) {
+    let accrued = income_debt(shares, acc_income_per_share).saturating_sub(*reward_debt);
+    *pending_income += (accrued / INCOME_PRECISION) as u64;
+    *reward_debt += accrued - accrued % INCOME_PRECISION;
+}
+
+// Helper function to compute the accumulator checkpoint for a share amount
+fn income_debt(shares: u64, acc_income_per_share: u128) -> u128 {
+    (shares as u128) * acc_income_per_share
+}
+
+// Helper function to value earn-on-spend rewards in reward token base units.
+// The reward is `earn_ratio` bps of the USDT spent, converted one whole USDT to one whole token.
+fn spend_reward_amount(amount_usdt: u64, earn_ratio: u64, usdt_decimals: u8, reward_decimals: u8) -> Result<u64> {
+    let reward_usdt = (amount_usdt as u128) * (earn_ratio as u128) / 10_000;
+    usdt_to_reward_units(reward_usdt, usdt_decimals, reward_decimals)
+}
+
+// Helper function to derive a merchant's daily reward cap from the per-customer cap
+fn merchant_daily_reward_cap(max_rewards_per_user: u64) -> u64 {
+    max_rewards_per_user.saturating_mul(MERCHANT_DAILY_CAP_MULTIPLIER)
+}
+
+// Helper function to convert USDT base units into reward token base units, one whole token for one whole USDT
+fn usdt_to_reward_units(amount_usdt: u128, usdt_decimals: u8, reward_decimals: u8) -> Result<u64> {
+    let scaled = if reward_decimals >= usdt_decimals {
+        amount_usdt
+            .checked_mul(10u128.pow((reward_decimals - usdt_decimals) as u32))
+            .ok_or(CustomError::InsufficientRewardFunds)?
+    } else {
+        amount_usdt / 10u128.pow((usdt_decimals - reward_decimals) as u32)
+    };
+    u64::try_from(scaled).map_err(|_| error!(CustomError::InsufficientRewardFunds))
+}
+
+// Helper function to compute the vested amount of a cliff + linear schedule at `now`
+pub fn vested_amount(total_amount: u64, start_ts: i64, cliff_ts: i64, end_ts: i64, now: i64) -> u64 {
+    if now < cliff_ts {
+        return 0;
+    }
+    if now >= end_ts {
+        return total_amount;
+    }
+    let elapsed = (now - start_ts) as u128;
+    let duration = (end_ts - start_ts) as u128;
+    ((total_amount as u128) * elapsed / duration) as u64
+}
+
+// Helper function to compute decaying veRAC voting power.
+// A maximum-length lock counts 1:1; shorter remaining time scales linearly down to zero at expiry.
+pub fn ve_voting_power(amount: u64, lock_end: i64, now: i64) -> u64 {
+    if lock_end <= now {
+        return 0;
+    }
+    let remaining = (lock_end - now).min(MAX_LOCK_DURATION) as u128;
+    ((amount as u128) * remaining / (MAX_LOCK_DURATION as u128)) as u64
+}
+
+// Helper function to sum the voting power delegated to `delegate` for one proposal.
+// Each delegator's lock is held until `end_slot` so it cannot be redelegated or withdrawn mid-vote.
+fn apply_delegated_weight(
+    remaining_accounts: &[AccountInfo],
+    delegate: &Pubkey,
+    dao_org: u8,
+    end_slot: u64,
+    now: i64,
+) -> Result<u64> {
+    require!(remaining_accounts.len() % 2 == 0, CustomError::InvalidDelegationAccounts);
+
+    let mut delegated_weight: u64 = 0;
+    let mut seen_delegators: Vec<Pubkey> = Vec::new();
+
+    for pair in remaining_accounts.chunks(2) {
+        let (delegation_info, lock_info) = (&pair[0], &pair[1]);
+        require!(
+            delegation_info.owner == &crate::ID && lock_info.owner == &crate::ID && lock_info.is_writable,
+            CustomError::InvalidDelegationAccounts
+        );
+
+        let delegation = Delegation::try_deserialize(&mut &delegation_info.try_borrow_data()?[..])?;
+        let delegation_address = Pubkey::create_program_address(
+            &[b"delegation", delegation.delegator.as_ref(), &[delegation.bump]],
+            &crate::ID,
+        ).map_err(|_| error!(CustomError::InvalidDelegationAccounts))?;
+        require_keys_eq!(delegation_address, delegation_info.key(), CustomError::InvalidDelegationAccounts);
+        require_keys_eq!(delegation.delegate, *delegate, CustomError::InvalidDelegationAccounts);
+        require!(!seen_delegators.contains(&delegation.delegator), CustomError::InvalidDelegationAccounts);
+        seen_delegators.push(delegation.delegator);
+
+        if !delegation.applies_to(dao_org) {
+            continue;
+        }
+
+        let mut ve_lock = VeLock::try_deserialize(&mut &lock_info.try_borrow_data()?[..])?;
+        let lock_address = Pubkey::create_program_address(
+            &[b"ve_lock", ve_lock.owner.as_ref(), &[ve_lock.bump]],
+            &crate::ID,
+        ).map_err(|_| error!(CustomError::InvalidDelegationAccounts))?;
+        require_keys_eq!(lock_address, lock_info.key(), CustomError::InvalidDelegationAccounts);
+        require_keys_eq!(ve_lock.owner, delegation.delegator, CustomError::InvalidDelegationAccounts);
+
+        let power = ve_voting_power(ve_lock.amount, ve_lock.lock_end, now);
+        if power == 0 {
+            continue;
+        }
+        delegated_weight += power;
+
+        if end_slot > ve_lock.locked_until_slot {
+            ve_lock.locked_until_slot = end_slot;
+            let mut data = lock_info.try_borrow_mut_data()?;
+            let mut writer: &mut [u8] = &mut data[..];
+            ve_lock.try_serialize(&mut writer)?;
+        }
+    }
+
+    Ok(delegated_weight)
+}
+
+// Helper function to validate DAO governance parameters
+fn validate_governance_params(quorum_bps: u16, voting_period_slots: u64, execution_delay: i64, grace_period: i64) -> Result<()> {
+    require!(quorum_bps <= 10_000, CustomError::InvalidQuorum);
+    require!(voting_period_slots > 0, CustomError::InvalidVotingPeriod);
+    require!(execution_delay >= 0 && grace_period > 0, CustomError::InvalidTimelockSettings);
+    Ok(())
+}
+
+// Helper function to check the proposer holds enough $RAC to open a proposal
+fn check_proposal_threshold(proposer_balance: u64, dao_config: &DaoConfig) -> Result<()> {
+    require!(proposer_balance >= dao_config.min_proposal_threshold, CustomError::BelowProposalThreshold);
+    Ok(())
+}
+
+// Helper function to check that an approved proposal is inside its execution window
+fn check_timelock(proposal: &Proposal, dao_config: &DaoConfig, now: i64) -> Result<()> {
+    require!(now >= proposal.eta, CustomError::TimelockNotExpired);
+    require!(now <= proposal.eta + dao_config.grace_period, CustomError::ProposalExpired);
+    Ok(())
+}
+
+// Helper function to add to a supply counter without passing the $RAC cap
+fn checked_supply_add(current: u64, amount: u64, cap: u64) -> Result<u64> {
+    let total = current.checked_add(amount).ok_or(CustomError::MaxSupplyExceeded)?;
+    require!(total <= cap, CustomError::MaxSupplyExceeded);
+    Ok(total)
+}
+
+// Helper function to check whether enough of the minted supply took part in a vote.
+// Quorum is measured against RAC that exists, not the `total_supply` cap.
+fn is_quorum_met(total_votes: u64, minted_supply: u64, quorum_bps: u16) -> bool {
+    (total_votes as u128) * 10_000 >= (minted_supply as u128) * (quorum_bps as u128)
+}
+
+// Helper function to get change type name
+fn get_change_type_name(change_type: u8) -> &'static str {
+    match change_type {
+        0 => "Point Release Delay",
+        1 => "Referral Parameters",
+        2 => "NFT Earning Ratios",
+        3 => "Loyalty Network Settings",
+        4 => "Merchant Limits",
+        5 => "Inactivity Timeout",
+        6 => "SMS OTP Settings",
+        7 => "Subscription Plans",
+        8 => "Asset Initiative Selection",
+        9 => "Wallet Management",
+        10 => "Payment Gateway",
+        11 => "Email Notifications",
+        _ => "Unknown Change Type",
+    }
+}
+
+/// Auto-staking of earned rewards. A position locks for its NFT's `auto_staking_duration`
+/// and each new stake moves the unlock time to a full duration from now; `Forever` never unlocks.
+pub mod staking {
+    use super::*;
+
+    /// Unlock time for rewards staked at `now`.
+    pub fn unlock_time(duration: AutoStakingDuration, now: i64) -> i64 {
+        duration.lockup_seconds().map_or(i64::MAX, |lockup| now.saturating_add(lockup))
+    }
+
+    /// Adds `amount` to a position and extends its lock.
+    pub fn add_stake(position: &mut RewardStake, amount: u64, duration: AutoStakingDuration, now: i64) {
+        position.amount += amount;
+        position.unlock_at = position.unlock_at.max(unlock_time(duration, now));
+        position.last_staked_at = now;
+    }
+
+    /// Rejects unstaking before the lock expires or beyond the staked amount.
+    pub fn check_unstake(position: &RewardStake, amount: u64, now: i64) -> Result<()> {
+        require!(amount > 0, CustomError::ZeroWithdrawalAmount);
+        require!(amount <= position.amount, CustomError::InsufficientStake);
+        require!(now >= position.unlock_at, CustomError::StakeLocked);
+        Ok(())
+    }
+}
+
+/// Layouts written before accounts carried a `version` byte, and their conversion to the
+/// current layouts. Unversioned accounts were always allocated with their layout's fixed
+/// `LEN`, so the data length tells the revisions apart.
+pub mod migration {
+    use super::*;
+
+    /// Values for `DaoConfig` fields that older layouts did not store. Fields a stored
+    /// layout already has are kept and the matching values here are ignored.
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+    pub struct DaoConfigMigrationArgs {
+        pub quorum_bps: u16,
+        pub voting_period_slots: u64,
+        pub min_proposal_threshold: u64,
+        pub admin_authority: Pubkey,
+        pub execution_delay: i64,
+        pub grace_period: i64,
+        pub guardian: Pubkey,
+        pub usdt_mint: Pubkey,
+    }
+
+    /// `DaoConfig` as first deployed (see `solana-dao-nft-contract-updated.rs`).
+    /// `total_supply` was stored in whole tokens.
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct DaoConfigRev1 {
+        pub rac_mint: Pubkey,
+        pub total_supply: u64,
+        pub proposal_count: u64,
+    }
+
+    impl DaoConfigRev1 {
+        pub const LEN: usize = 8 + 32 + 8 + 8;
+
+        pub fn into_current(self, args: &DaoConfigMigrationArgs, rac_supply: u64) -> DaoConfig {
+            DaoConfigRev2 {
+                rac_mint: self.rac_mint,
+                total_supply: self.total_supply,
+                proposal_count: self.proposal_count,
+                quorum_bps: args.quorum_bps,
+                voting_period_slots: args.voting_period_slots,
+                min_proposal_threshold: args.min_proposal_threshold,
+                admin_authority: args.admin_authority,
+            }
+            .into_current(args, rac_supply)
+        }
+    }
+
+    /// `DaoConfig` with governance parameters, before supply tracking, the timelock and the treasury.
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct DaoConfigRev2 {
+        pub rac_mint: Pubkey,
+        pub total_supply: u64,
+        pub proposal_count: u64,
+        pub quorum_bps: u16,
+        pub voting_period_slots: u64,
+        pub min_proposal_threshold: u64,
+        pub admin_authority: Pubkey,
+    }
+
+    impl DaoConfigRev2 {
+        pub const LEN: usize = 8 + 32 + 8 + 8 + 2 + 8 + 8 + 32;
+
+        pub fn into_current(self, args: &DaoConfigMigrationArgs, rac_supply: u64) -> DaoConfig {
+            DaoConfig {
+                version: ACCOUNT_VERSION,
+                rac_mint: self.rac_mint,
+                total_supply: RAC_MAX_SUPPLY, // Stored in whole tokens before; now in base units
+                minted_supply: rac_supply,
+                vesting_allocated: 0,
+                proposal_count: self.proposal_count,
+                quorum_bps: self.quorum_bps,
+                voting_period_slots: self.voting_period_slots,
+                min_proposal_threshold: self.min_proposal_threshold,
+                admin_authority: self.admin_authority,
+                execution_delay: args.execution_delay,
+                grace_period: args.grace_period,
+                guardian: args.guardian,
+                usdt_mint: args.usdt_mint,
+            }
+        }
+    }
+
+    /// Last unversioned `DaoConfig` layout: the current fields without `version`.
+    pub const DAO_CONFIG_REV3_LEN: usize = DaoConfig::LEN - 1;
+
+    /// Decodes any `DaoConfig` revision and converts it to the current layout.
+    pub fn upgrade_dao_config(data: &[u8], args: &DaoConfigMigrationArgs, rac_supply: u64) -> Result<DaoConfig> {
+        require!(data.len() > 8 && data[..8] == DaoConfig::DISCRIMINATOR, CustomError::UnknownAccountLayout);
+        let body = &mut &data[8..];
+        match data.len() {
+            DaoConfig::LEN => err!(CustomError::AlreadyMigrated),
+            DAO_CONFIG_REV3_LEN => decode_unversioned(&data[8..]),
+            DaoConfigRev2::LEN => Ok(DaoConfigRev2::deserialize(body)?.into_current(args, rac_supply)),
+            DaoConfigRev1::LEN => Ok(DaoConfigRev1::deserialize(body)?.into_current(args, rac_supply)),
+            _ => err!(CustomError::UnknownAccountLayout),
+        }
+    }
+
+    /// `Proposal` as first deployed (see `solana-dao-nft-contract-updated.rs`), with no lifecycle.
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct ProposalRev1 {
+        pub proposal_id: u64,
+        pub proposer: Pubkey,
+        pub proposal_type: u8,
+        pub description: String,
+        pub votes_for: u64,
+        pub votes_against: u64,
+    }
+
+    impl ProposalRev1 {
+        pub const LEN: usize = 8 + 8 + 32 + 1 + (4 + 200) + 8 + 8;
+
+        /// These proposals never had a voting window, so they cannot be resumed. They are
+        /// kept for the record as cancelled, with their tallies.
+        pub fn into_current(self) -> Proposal {
+            ProposalRev2 {
+                proposal_id: self.proposal_id,
+                proposer: self.proposer,
+                proposal_type: self.proposal_type,
+                description: self.description,
+                votes_for: self.votes_for,
+                votes_against: self.votes_against,
+                dao_org: 0,
+                status: ProposalStatus::Cancelled as u8,
+                created_at: 0,
+                end_slot: 0,
+                executed_at: None,
+            }
+            .into_current()
+        }
+    }
+
+    /// `Proposal` with `dao_org` and the lifecycle fields, before abstentions and stored instructions.
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct ProposalRev2 {
+        pub proposal_id: u64,
+        pub proposer: Pubkey,
+        pub proposal_type: u8,
+        pub description: String,
+        pub votes_for: u64,
+        pub votes_against: u64,
+        pub dao_org: u8,
+        pub status: u8,
+        pub created_at: i64,
+        pub end_slot: u64,
+        pub executed_at: Option<i64>,
+    }
+
+    impl ProposalRev2 {
+        pub const LEN: usize = 8 + 8 + 32 + 1 + (4 + 200) + 8 + 8 + 1 + 1 + 8 + 8 + (1 + 8);
+
+        pub fn into_current(self) -> Proposal {
+            Proposal {
+                version: ACCOUNT_VERSION,
+                proposal_id: self.proposal_id,
+                proposer: self.proposer,
+                proposal_type: self.proposal_type,
+                description: self.description,
+                votes_for: self.votes_for,
+                votes_against: self.votes_against,
+                votes_abstain: 0,
+                dao_org: self.dao_org,
+                status: self.status,
+                created_at: self.created_at,
+                end_slot: self.end_slot,
+                executed_at: self.executed_at,
+                instruction_count: 0,
+                instructions_executed: 0,
+                eta: 0,
+            }
+        }
+    }
+
+    /// Last unversioned `Proposal` layout: the current fields without `version`.
+    pub const PROPOSAL_REV3_LEN: usize = Proposal::LEN - 1;
+
+    /// Decodes any `Proposal` revision and converts it to the current layout.
+    pub fn upgrade_proposal(data: &[u8]) -> Result<Proposal> {
+        require!(data.len() > 8 && data[..8] == Proposal::DISCRIMINATOR, CustomError::UnknownAccountLayout);
+        let body = &mut &data[8..];
+        match data.len() {
+            Proposal::LEN => err!(CustomError::AlreadyMigrated),
+            PROPOSAL_REV3_LEN => decode_unversioned(&data[8..]),
+            ProposalRev2::LEN => Ok(ProposalRev2::deserialize(body)?.into_current()),
+            ProposalRev1::LEN => Ok(ProposalRev1::deserialize(body)?.into_current()),
+            _ => err!(CustomError::UnknownAccountLayout),
+        }
+    }
+
+    /// `NftAccount` as first deployed, with free-form String collection, rarity and duration.
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct NftAccountRev1 {
+        pub admin: Pubkey,
+        pub collection_name: String,
+        pub nft_name: String,
+        pub display_name: String,
+        pub symbol: String,
+        pub uri: String,
+        pub custody_type: CustodyType,
+        pub buy_price_usdt: u64,
+        pub rarity: String,
+        pub mint_quantity: u64,
+        pub is_upgradeable: bool,
+        pub is_evolvable: bool,
+        pub is_fractional_eligible: bool,
+        pub auto_staking_duration: String,
+        pub earn_on_spend_ratio: u64,
+        pub upgrade_bonus_ratio: u64,
+        pub evolution_min_investment: u64,
+        pub evolution_earnings_ratio: u64,
+        pub passive_income_rate: u64,
+        pub custodial_income_rate: Option<u64>,
+        pub last_distribution_timestamp: i64,
+        pub is_active: bool,
+        pub created_at: i64,
+        pub updated_at: i64,
+    }
+
+    impl NftAccountRev1 {
+        pub const LEN: usize = 8 + 32 + (4 + 50) + (4 + 10) + (4 + 200) + 1 + 8 + (1 + 8) + 8;
+
+        pub fn into_current(self) -> Result<NftAccount> {
+            NftAccountRev2 {
+                admin: self.admin,
+                collection_name: self.collection_name,
+                nft_name: self.nft_name,
+                display_name: self.display_name,
+                symbol: self.symbol,
+                uri: self.uri,
+                custody_type: self.custody_type,
+                buy_price_usdt: self.buy_price_usdt,
+                rarity: self.rarity,
+                mint_quantity: self.mint_quantity,
+                is_upgradeable: self.is_upgradeable,
+                is_evolvable: self.is_evolvable,
+                is_fractional_eligible: self.is_fractional_eligible,
+                auto_staking_duration: self.auto_staking_duration,
+                earn_on_spend_ratio: self.earn_on_spend_ratio,
+                upgrade_bonus_ratio: self.upgrade_bonus_ratio,
+                evolution_min_investment: self.evolution_min_investment,
+                evolution_earnings_ratio: self.evolution_earnings_ratio,
+                passive_income_rate: self.passive_income_rate,
+                custodial_income_rate: self.custodial_income_rate,
+                last_distribution_timestamp: self.last_distribution_timestamp,
+                acc_income_per_share: 0,
+                total_shares: 0,
+                evolved_uri: String::new(),
+                is_active: self.is_active,
+                created_at: self.created_at,
+                updated_at: self.updated_at,
+            }
+            .into_current()
+        }
+    }
+
+    /// `NftAccount` with passive income accounting and evolution metadata, still using Strings.
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct NftAccountRev2 {
+        pub admin: Pubkey,
+        pub collection_name: String,           // Collection Name (dropdown list)
+        pub nft_name: String,                  // NFT Name (e.g., Pearl White, Lava Orange)
+        pub display_name: String,              // Display Name
+        pub symbol: String,
+        pub uri: String,
+        pub custody_type: CustodyType,
+
+        // Pricing & Minting
+        pub buy_price_usdt: u64,               // Buy Price in USDT (in smallest unit)
+        pub rarity: String,                    // Common, Less Common, Rare, Very Rare
+        pub mint_quantity: u64,                // Total number that can be minted
+
+        // Features & Capabilities
+        pub is_upgradeable: bool,              // Upgrade (Yes/No)
+        pub is_evolvable: bool,                // Evolve (Yes/No)
+        pub is_fractional_eligible: bool,      // Fractional (Yes/No)
+
+        // Auto Staking
+        pub auto_staking_duration: String,
+
+        // Earning Ratios
+        pub earn_on_spend_ratio: u64,          // Earn on Spend % (in basis points, e.g., 100 = 1.00%)
+        pub upgrade_bonus_ratio: u64,          // Upgrade Bonus Tokenization % (in basis points)
+
+        // Evolution Settings
+        pub evolution_min_investment: u64,     // Evolution Min Invest in USDT (in smallest unit)
+        pub evolution_earnings_ratio: u64,     // Evolution Earnings % (in basis points)
+
+        // Legacy fields for backward compatibility
+        pub passive_income_rate: u64,
+        pub custodial_income_rate: Option<u64>,
+        pub last_distribution_timestamp: i64,
+
+        // Passive income accounting
+        pub acc_income_per_share: u128,        // Income per invested unit, scaled by INCOME_PRECISION
+        pub total_shares: u64,                 // Sum of all investment amounts for this NFT
+
+        // Evolution metadata
+        pub evolved_uri: String,               // Metadata URI for evolved cards
+
+        // Metadata
+        pub is_active: bool,
+        pub created_at: i64,
+        pub updated_at: i64,
+    }
+
+    impl NftAccountRev2 {
+        pub const LEN: usize = 8 + 32 + (4 + 50) + (4 + 10) + (4 + 200) + 1 + 8 + (1 + 8) + 8 + 16 + 8 + (4 + 200);
+
+        pub fn into_current(self) -> Result<NftAccount> {
+            Ok(NftAccount {
+                version: ACCOUNT_VERSION,
+                admin: self.admin,
+                collection: Collection::from_legacy(&self.collection_name)?,
+                nft_name: self.nft_name,
+                display_name: self.display_name,
+                symbol: self.symbol,
+                uri: self.uri,
+                custody_type: self.custody_type,
+                buy_price_usdt: self.buy_price_usdt,
+                rarity: Rarity::from_legacy(&self.rarity)?,
+                mint_quantity: self.mint_quantity,
+                minted_count: 0,
+                is_upgradeable: self.is_upgradeable,
+                is_evolvable: self.is_evolvable,
+                is_fractional_eligible: self.is_fractional_eligible,
+                auto_staking_duration: AutoStakingDuration::from_legacy(&self.auto_staking_duration)?,
+                earn_on_spend_ratio: self.earn_on_spend_ratio,
+                upgrade_bonus_ratio: self.upgrade_bonus_ratio,
+                evolution_min_investment: self.evolution_min_investment,
+                evolution_earnings_ratio: self.evolution_earnings_ratio,
+                passive_income_rate: self.passive_income_rate,
+                custodial_income_rate: self.custodial_income_rate,
+                last_distribution_timestamp: self.last_distribution_timestamp,
+                acc_income_per_share: self.acc_income_per_share,
+                total_shares: self.total_shares,
+                evolved_uri: self.evolved_uri,
+                is_active: self.is_active,
+                created_at: self.created_at,
+                updated_at: self.updated_at,
+            })
+        }
+    }
+
+    /// Decodes any unversioned `NftAccount` revision and converts it to the current layout.
+    pub fn upgrade_nft_account(data: &[u8]) -> Result<NftAccount> {
+        require!(data.len() > 8 && data[..8] == NftAccount::DISCRIMINATOR, CustomError::InvalidNftAccount);
+        require!(
+            !(data.len() == NftAccount::LEN && data[8] == ACCOUNT_VERSION),
+            CustomError::AlreadyMigrated
+        );
+        let body = &mut &data[8..];
+        match data.len() {
+            NftAccountRev1::LEN => NftAccountRev1::deserialize(body)?.into_current(),
+            NftAccountRev2::LEN => NftAccountRev2::deserialize(body)?.into_current(),
+            _ => err!(CustomError::UnknownAccountLayout),
+        }
+    }
+
+    /// `CustodialInvestment` as first deployed. That program recorded the amount without taking
+    /// any USDT, so nothing in the escrow backs it.
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct CustodialInvestmentRev1 {
+        pub investor: Pubkey,
+        pub nft: Pubkey,
+        pub amount: u64,
+    }
+
+    impl CustodialInvestmentRev1 {
+        pub const LEN: usize = 8 + 32 + 32 + 8;
+
+        /// The position starts empty, since withdrawing the unbacked amount would pay it out of
+        /// other investors' escrow.
+        pub fn into_current(self) -> CustodialInvestment {
+            CustodialInvestment {
+                version: ACCOUNT_VERSION,
+                investor: self.investor,
+                nft: self.nft,
+                amount: 0,
+                reward_debt: 0,
+                pending_income: 0,
+                last_claim_at: 0,
+                unlock_at: 0,
+            }
+        }
+    }
+
+    /// `NonCustodialInvestment` as first deployed. `amount` counted shares minted straight to
+    /// the investor's wallet.
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct NonCustodialInvestmentRev1 {
+        pub investor: Pubkey,
+        pub nft: Pubkey,
+        pub amount: u64,
+    }
+
+    impl NonCustodialInvestmentRev1 {
+        pub const LEN: usize = 8 + 32 + 32 + 8;
+
+        /// `amount` now counts escrowed shares. The investor's shares are still in their wallet,
+        /// so the position starts empty until they `deposit_fractional_shares`.
+        pub fn into_current(self) -> NonCustodialInvestment {
+            NonCustodialInvestment {
+                version: ACCOUNT_VERSION,
+                investor: self.investor,
+                nft: self.nft,
+                amount: 0,
+                reward_debt: 0,
+                pending_income: 0,
+                last_claim_at: 0,
+            }
+        }
+    }
+
+    /// `FractionalNft` as first deployed. Shares were minted to investors on purchase, so
+    /// `total_supply` counted shares already sold.
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct FractionalNftRev1 {
+        pub parent_nft: Pubkey,
+        pub mint: Pubkey,
+        pub total_supply: u64,
+        pub supply_cap: u64,
+    }
+
+    impl FractionalNftRev1 {
+        pub const LEN: usize = 8 + 32 + 32 + 8 + 8;
+
+        pub fn into_current(self) -> FractionalNft {
+            FractionalNft {
+                version: ACCOUNT_VERSION,
+                parent_nft: self.parent_nft,
+                mint: self.mint,
+                total_supply: self.total_supply,
+                supply_cap: self.supply_cap,
+                sold: self.total_supply,
+            }
+        }
+    }
+
+    /// `VoterRecord` as first deployed, marking only that the voter had voted.
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct VoterRecordRev1 {
+        pub voter: Pubkey,
+        pub proposal_id: u64,
+    }
+
+    impl VoterRecordRev1 {
+        pub const LEN: usize = 8 + 32 + 8;
+
+        /// The choice and weight were never stored. Their proposals migrate as `Cancelled`, so
+        /// the record is kept as a zero-weight abstention.
+        pub fn into_current(self) -> VoterRecord {
+            VoterRecord {
+                version: ACCOUNT_VERSION,
+                voter: self.voter,
+                proposal_id: self.proposal_id,
+                choice: VoteChoice::Abstain,
+                weight: 0,
+                delegated_weight: 0,
+            }
+        }
+    }
+
+    /// `RewardsConfig` as first deployed, before the reward vault and income tracking.
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct RewardsConfigRev1 {
+        pub program_id: Pubkey,
+        pub admin_authority: Pubkey,
+        pub reward_token_mint: Pubkey,
+        pub distribution_interval: u64,
+        pub max_rewards_per_user: u64,
+        pub is_active: bool,
+        pub created_at: i64,
+        pub updated_at: i64,
+    }
+
+    impl RewardsConfigRev1 {
+        pub const LEN: usize = 8 + 32 + 32 + 32 + 8 + 8 + 1 + 8 + 8;
+
+        pub fn into_current(self, reward_vault: Pubkey, bump: u8) -> RewardsConfig {
+            RewardsConfig {
+                version: ACCOUNT_VERSION,
+                program_id: self.program_id,
+                admin_authority: self.admin_authority,
+                reward_token_mint: self.reward_token_mint,
+                distribution_interval: self.distribution_interval,
+                max_rewards_per_user: self.max_rewards_per_user,
+                reward_vault,
+                outstanding_income: 0,
+                is_active: self.is_active,
+                created_at: self.created_at,
+                updated_at: self.updated_at,
+                bump,
+            }
+        }
+    }
+
+    /// Decodes the original `RewardsConfig` and converts it to the current layout.
+    pub fn upgrade_rewards_config(data: &[u8], reward_vault: Pubkey, bump: u8) -> Result<RewardsConfig> {
+        require!(data.len() > 8 && data[..8] == RewardsConfig::DISCRIMINATOR, CustomError::UnknownAccountLayout);
+        let body = &mut &data[8..];
+        match data.len() {
+            RewardsConfig::LEN => err!(CustomError::AlreadyMigrated),
+            RewardsConfigRev1::LEN => Ok(RewardsConfigRev1::deserialize(body)?.into_current(reward_vault, bump)),
+            _ => err!(CustomError::UnknownAccountLayout),
+        }
+    }
+
+    /// Converts any other fixed-size account to the current layout and returns its new data.
+    /// Layouts that only lack the `version` byte have it inserted; the original investment,
+    /// fractional pool and voter record layouts go through their `Rev1` conversion.
+    pub fn upgrade_fixed_account(data: &[u8]) -> Result<Vec<u8>> {
+        let current_len = versioned_account_len(data)?;
+        require!(data.len() != current_len, CustomError::AlreadyMigrated);
+        let discriminator = &data[..8];
+        let body = &mut &data[8..];
+
+        if data.len() + 1 == current_len {
+            let mut migrated = data[..8].to_vec();
+            migrated.push(ACCOUNT_VERSION);
+            migrated.extend_from_slice(&data[8..]);
+            return Ok(migrated);
+        }
+
+        match data.len() {
+            CustodialInvestmentRev1::LEN if discriminator == CustodialInvestment::DISCRIMINATOR => {
+                serialize_account(&CustodialInvestmentRev1::deserialize(body)?.into_current())
+            }
+            NonCustodialInvestmentRev1::LEN if discriminator == NonCustodialInvestment::DISCRIMINATOR => {
+                serialize_account(&NonCustodialInvestmentRev1::deserialize(body)?.into_current())
+            }
+            FractionalNftRev1::LEN if discriminator == FractionalNft::DISCRIMINATOR => {
+                serialize_account(&FractionalNftRev1::deserialize(body)?.into_current())
+            }
+            VoterRecordRev1::LEN if discriminator == VoterRecord::DISCRIMINATOR => {
+                serialize_account(&VoterRecordRev1::deserialize(body)?.into_current())
+            }
+            _ => err!(CustomError::UnknownAccountLayout),
+        }
+    }
+
+    // Serializes an account with its discriminator. Fixed-size accounts fill exactly their `LEN`.
+    fn serialize_account<T: AccountSerialize>(account: &T) -> Result<Vec<u8>> {
+        let mut data = Vec::new();
+        account.try_serialize(&mut data)?;
+        Ok(data)
+    }
+
+    /// Current length of a fixed-size account type whose only change is the `version` byte.
+    pub fn versioned_account_len(data: &[u8]) -> Result<usize> {
+        require!(data.len() > 8, CustomError::UnknownAccountLayout);
+        let discriminator = &data[..8];
+        let layouts: [([u8; 8], usize); 19] = [
+            (CustodialInvestment::DISCRIMINATOR, CustodialInvestment::LEN),
+            (NonCustodialInvestment::DISCRIMINATOR, NonCustodialInvestment::LEN),
+            (FractionalNft::DISCRIMINATOR, FractionalNft::LEN),
+            (VoterRecord::DISCRIMINATOR, VoterRecord::LEN),
+            (Delegation::DISCRIMINATOR, Delegation::LEN),
+            (VeLock::DISCRIMINATOR, VeLock::LEN),
+            (TreasuryAsset::DISCRIMINATOR, TreasuryAsset::LEN),
+            (VestingSchedule::DISCRIMINATOR, VestingSchedule::LEN),
+            (CardCollection::DISCRIMINATOR, CardCollection::LEN),
+            (LoyaltyCard::DISCRIMINATOR, LoyaltyCard::LEN),
+            (CardTree::DISCRIMINATOR, CardTree::LEN),
+            (RewardStake::DISCRIMINATOR, RewardStake::LEN),
+            (Merchant::DISCRIMINATOR, Merchant::LEN),
+            (SpendReceipt::DISCRIMINATOR, SpendReceipt::LEN),
+            (SpendTracker::DISCRIMINATOR, SpendTracker::LEN),
+            (RewardsConfig::DISCRIMINATOR, RewardsConfig::LEN),
+            (ConfigProposal::DISCRIMINATOR, ConfigProposal::LEN),
+            (DaoConfigProposal::DISCRIMINATOR, DaoConfigProposal::LEN),
+            (LoyaltyProposal::DISCRIMINATOR, LoyaltyProposal::LEN),
+        ];
+        layouts
+            .iter()
+            .find(|(layout_discriminator, _)| layout_discriminator[..] == *discriminator)
+            .map(|(_, len)| *len)
+            .ok_or_else(|| error!(CustomError::UnknownAccountLayout))
+    }
+
+    // Decodes an account body stored in the current layout minus the leading `version` byte.
+    fn decode_unversioned<T: AnchorDeserialize>(body: &[u8]) -> Result<T> {
+        let mut versioned = Vec::with_capacity(body.len() + 1);
+        versioned.push(ACCOUNT_VERSION);
+        versioned.extend_from_slice(body);
+        Ok(T::deserialize(&mut &versioned[..])?)
+    }
+}
+
+// Account contexts for existing functions remain the same...
+
+/// The account context for the `create_rac_mint` instruction.
+#[derive(Accounts)]
+pub struct CreateRacMint<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = DaoConfig::LEN,
+        seeds = [b"dao_config"],
+        bump
+    )]
+    pub dao_config: Account<'info, DaoConfig>,
+    #[account(
+        init,
+        payer = admin,
+        mint::decimals = RAC_DECIMALS,
+        mint::authority = dao_authority,
+    )]
+    pub rac_mint: Account<'info, Mint>,
+    #[account(
+        init,
+        payer = admin,
+        token::mint = rac_mint,
+        token::authority = dao_authority,
+        seeds = [b"treasury_vault", rac_mint.key().as_ref()],
+        bump
+    )]
+    pub dao_vault: Account<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = admin,
+        space = TreasuryAsset::LEN,
+        seeds = [b"treasury_asset", rac_mint.key().as_ref()],
+        bump
+    )]
+    pub treasury_asset: Account<'info, TreasuryAsset>,
+    /// CHECK: PDA that holds the $RAC mint authority and owns the treasury vaults. Holds no data.
+    #[account(seeds = [b"dao_authority"], bump)]
+    pub dao_authority: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/// The account context for funding the DAO authority PDA.
+#[derive(Accounts)]
+pub struct FundDaoAuthority<'info> {
+    /// CHECK: PDA that signs governed instructions. Holds no data.
+    #[account(mut, seeds = [b"dao_authority"], bump)]
+    pub dao_authority: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub funder: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// The account context for minting $RAC through governance.
+#[derive(Accounts)]
+pub struct MintRac<'info> {
+    #[account(mut, seeds = [b"dao_config"], bump)]
+    pub dao_config: Account<'info, DaoConfig>,
+    #[account(mut, address = dao_config.rac_mint @ CustomError::InvalidRacMint)]
+    pub rac_mint: Account<'info, Mint>,
+    #[account(mut, seeds = [b"treasury_vault", rac_mint.key().as_ref()], bump)]
+    pub dao_vault: Account<'info, TokenAccount>,
+    #[account(mut, seeds = [b"treasury_asset", rac_mint.key().as_ref()], bump = treasury_asset.bump)]
+    pub treasury_asset: Account<'info, TreasuryAsset>,
+    #[account(seeds = [b"dao_authority"], bump)]
+    pub dao_authority: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+/// The account context for opening a treasury vault.
+#[derive(Accounts)]
+pub struct InitializeTreasuryAsset<'info> {
+    #[account(seeds = [b"dao_config"], bump, has_one = admin_authority @ CustomError::UnauthorizedAdmin)]
+    pub dao_config: Account<'info, DaoConfig>,
+    pub mint: Account<'info, Mint>,
+    #[account(
+        init,
+        payer = admin_authority,
+        space = TreasuryAsset::LEN,
+        seeds = [b"treasury_asset", mint.key().as_ref()],
+        bump
+    )]
+    pub treasury_asset: Account<'info, TreasuryAsset>,
+    #[account(
+        init,
+        payer = admin_authority,
+        token::mint = mint,
+        token::authority = dao_authority,
+        seeds = [b"treasury_vault", mint.key().as_ref()],
+        bump
+    )]
+    pub treasury_vault: Account<'info, TokenAccount>,
+    /// CHECK: PDA that owns the treasury vaults. Holds no data.
+    #[account(seeds = [b"dao_authority"], bump)]
+    pub dao_authority: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub admin_authority: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/// The account context for depositing into the treasury.
+#[derive(Accounts)]
+pub struct DepositToTreasury<'info> {
+    #[account(mut, seeds = [b"treasury_asset", treasury_asset.mint.as_ref()], bump = treasury_asset.bump)]
+    pub treasury_asset: Account<'info, TreasuryAsset>,
+    #[account(mut, address = treasury_asset.vault)]
+    pub treasury_vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = depositor_token_account.mint == treasury_asset.mint @ CustomError::UnsupportedTreasuryAsset
+    )]
+    pub depositor_token_account: Account<'info, TokenAccount>,
+    pub depositor: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+/// The account context for withdrawing from the treasury.
+#[derive(Accounts)]
+pub struct WithdrawFromTreasury<'info> {
+    #[account(mut, seeds = [b"treasury_asset", treasury_asset.mint.as_ref()], bump = treasury_asset.bump)]
+    pub treasury_asset: Account<'info, TreasuryAsset>,
+    #[account(mut, address = treasury_asset.vault)]
+    pub treasury_vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = recipient_token_account.mint == treasury_asset.mint @ CustomError::UnsupportedTreasuryAsset
+    )]
+    pub recipient_token_account: Account<'info, TokenAccount>,
+    #[account(seeds = [b"dao_authority"], bump)]
+    pub dao_authority: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+/// The account context for creating a vesting schedule.
+#[derive(Accounts)]
+#[instruction(schedule_id: u64)]
+pub struct CreateVestingSchedule<'info> {
+    #[account(mut, seeds = [b"dao_config"], bump)]
+    pub dao_config: Account<'info, DaoConfig>,
+    #[account(address = dao_config.rac_mint @ CustomError::InvalidRacMint)]
+    pub rac_mint: Account<'info, Mint>,
+    #[account(
+        init,
+        payer = dao_authority,
+        space = VestingSchedule::LEN,
+        seeds = [b"vesting", beneficiary.key().as_ref(), schedule_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub vesting_schedule: Account<'info, VestingSchedule>,
+    #[account(
+        init,
+        payer = dao_authority,
+        token::mint = rac_mint,
+        token::authority = vesting_schedule,
+        seeds = [b"vesting_vault", vesting_schedule.key().as_ref()],
+        bump
+    )]
+    pub vesting_vault: Account<'info, TokenAccount>,
+    #[account(mut, seeds = [b"treasury_vault", rac_mint.key().as_ref()], bump)]
+    pub dao_vault: Account<'info, TokenAccount>,
+    #[account(mut, seeds = [b"treasury_asset", rac_mint.key().as_ref()], bump = treasury_asset.bump)]
+    pub treasury_asset: Account<'info, TreasuryAsset>,
+    /// CHECK: The wallet that receives the vested tokens.
+    pub beneficiary: UncheckedAccount<'info>,
+    // Pays rent for the schedule accounts from lamports sent with `fund_dao_authority`
+    #[account(mut, seeds = [b"dao_authority"], bump)]
+    pub dao_authority: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/// The account context for claiming vested tokens.
+#[derive(Accounts)]
+pub struct ClaimVested<'info> {
+    #[account(
+        mut,
+        seeds = [b"vesting", beneficiary.key().as_ref(), vesting_schedule.schedule_id.to_le_bytes().as_ref()],
+        bump = vesting_schedule.bump,
+        has_one = beneficiary @ CustomError::Unauthorized
+    )]
+    pub vesting_schedule: Account<'info, VestingSchedule>,
+    #[account(mut, seeds = [b"vesting_vault", vesting_schedule.key().as_ref()], bump)]
+    pub vesting_vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = beneficiary_token_account.mint == vesting_vault.mint @ CustomError::InvalidRacMint,
+        constraint = beneficiary_token_account.owner == beneficiary.key() @ CustomError::InvalidTokenOwner
+    )]
+    pub beneficiary_token_account: Account<'info, TokenAccount>,
+    pub beneficiary: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+/// The account context for revoking the unvested part of a schedule.
+#[derive(Accounts)]
+pub struct RevokeVesting<'info> {
+    #[account(mut, seeds = [b"dao_config"], bump)]
+    pub dao_config: Account<'info, DaoConfig>,
+    #[account(
+        mut,
+        seeds = [b"vesting", vesting_schedule.beneficiary.as_ref(), vesting_schedule.schedule_id.to_le_bytes().as_ref()],
+        bump = vesting_schedule.bump
+    )]
+    pub vesting_schedule: Account<'info, VestingSchedule>,
+    #[account(mut, seeds = [b"vesting_vault", vesting_schedule.key().as_ref()], bump)]
+    pub vesting_vault: Account<'info, TokenAccount>,
+    #[account(mut, seeds = [b"treasury_vault", dao_config.rac_mint.as_ref()], bump)]
+    pub dao_vault: Account<'info, TokenAccount>,
+    #[account(mut, seeds = [b"treasury_asset", dao_config.rac_mint.as_ref()], bump = treasury_asset.bump)]
+    pub treasury_asset: Account<'info, TreasuryAsset>,
+    #[account(seeds = [b"dao_authority"], bump)]
+    pub dao_authority: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+/// The account context for initializing rewards configuration.
+#[derive(Accounts)]
+pub struct InitializeRewardsConfig<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = RewardsConfig::LEN,
+        seeds = [b"rewards_config"],
+        bump
+    )]
+    pub rewards_config: Account<'info, RewardsConfig>,
+    #[account(mut)]
+    pub reward_token_mint: Account<'info, Mint>,
+    #[account(
+        init,
+        payer = admin,
+        token::mint = reward_token_mint,
+        token::authority = rewards_config,
+        seeds = [b"reward_vault"],
+        bump
+    )]
+    pub reward_vault: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    /// CHECK: This is the program ID
+    pub program_id: UncheckedAccount<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/// The account context for the `create_nft` instruction.
+#[derive(Accounts)]
+#[instruction(collection: Collection, nft_name: String, display_name: String, symbol: String, uri: String, is_custodial: bool)]
+pub struct CreateNft<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = NftAccount::LEN,
+        seeds = [b"nft", &[is_custodial as u8], nft_name.as_bytes(), symbol.as_bytes()],
+        bump
+    )]
+    pub nft: Account<'info, NftAccount>,
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = FractionalNft::LEN,
+        seeds = [b"fractional", nft.key().as_ref()],
+        bump,
+        constraint = !is_custodial @ CustomError::AccountShouldNotBeUsed
+    )]
+    pub fractional_nft: Option<Account<'info, FractionalNft>>,
+    #[account(
+        init_if_needed,
+        payer = admin,
+        mint::decimals = 6,
+        mint::authority = admin,
+        constraint = !is_custodial @ CustomError::AccountShouldNotBeUsed
+    )]
+    pub fractional_mint: Option<Account<'info, Mint>>,
+    #[account(
+        init_if_needed,
+        payer = admin,
+        token::mint = fractional_mint.as_ref().unwrap(),
+        token::authority = fractional_authority.as_ref().unwrap(),
+        seeds = [b"fractional_vault", nft.key().as_ref()],
+        bump,
+        constraint = !is_custodial @ CustomError::AccountShouldNotBeUsed
+    )]
+    pub fractional_vault: Option<Account<'info, TokenAccount>>,
+    /// CHECK: PDA that owns the fractional vault; holds no data
+    #[account(seeds = [b"fractional_authority", nft.key().as_ref()], bump)]
+    pub fractional_authority: Option<UncheckedAccount<'info>>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/// The account context for the `update_nft` instruction.
+#[derive(Accounts)]
+#[instruction(collection: Collection, nft_name: String, display_name: String, symbol: String)]
+pub struct UpdateNft<'info> {
+    #[account(
+        mut,
+        constraint = is_nft_address(&nft.key(), nft.custody_type, &nft_name, &symbol) @ CustomError::InvalidNftAccount,
+        constraint = nft.admin == admin.key() @ CustomError::Unauthorized
+    )]
+    pub nft: Account<'info, NftAccount>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+}
+
+/// The account context for the `initialize_catalog` instruction.
+/// The NFT PDAs are passed as remaining accounts, one per card tier spec.
+#[derive(Accounts)]
+pub struct InitializeCatalog<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// The account context for creating the fractional pool of an existing non-custodial NFT.
+#[derive(Accounts)]
+pub struct InitializeFractionalPool<'info> {
+    #[account(constraint = nft.admin == admin.key() @ CustomError::Unauthorized)]
+    pub nft: Account<'info, NftAccount>,
+    #[account(
+        init,
+        payer = admin,
+        space = FractionalNft::LEN,
+        seeds = [b"fractional", nft.key().as_ref()],
+        bump
+    )]
+    pub fractional_nft: Account<'info, FractionalNft>,
+    #[account(
+        init,
+        payer = admin,
+        mint::decimals = 6,
+        mint::authority = admin,
+    )]
+    pub fractional_mint: Account<'info, Mint>,
+    #[account(
+        init,
+        payer = admin,
+        token::mint = fractional_mint,
+        token::authority = fractional_authority,
+        seeds = [b"fractional_vault", nft.key().as_ref()],
+        bump
+    )]
+    pub fractional_vault: Account<'info, TokenAccount>,
+    /// CHECK: PDA that owns the fractional vault; holds no data
+    #[account(seeds = [b"fractional_authority", nft.key().as_ref()], bump)]
+    pub fractional_authority: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/// The account context for a user investing in a custodial NFT.
+#[derive(Accounts)]
+#[instruction(investment_amount: u64)]
+pub struct InvestInCustodialNft<'info> {
+    #[account(mut)]
+    pub nft: Account<'info, NftAccount>,
+    #[account(
+        // FIX: Allow multiple investments from the same user
+        init_if_needed,
+        payer = investor,
+        space = CustodialInvestment::LEN,
+        seeds = [b"investment", nft.key().as_ref(), investor.key().as_ref()],
+        bump
+    )]
+    pub investment_account: Account<'info, CustodialInvestment>,
+    #[account(seeds = [b"dao_config"], bump)]
+    pub dao_config: Account<'info, DaoConfig>,
+    #[account(address = dao_config.usdt_mint @ CustomError::InvalidInvestmentMint)]
+    pub investment_mint: Account<'info, Mint>,
+    #[account(
+        mut,
+        constraint = investor_token_account.mint == investment_mint.key() @ CustomError::InvalidInvestmentMint,
+        constraint = investor_token_account.owner == investor.key() @ CustomError::InvalidTokenOwner
+    )]
+    pub investor_token_account: Account<'info, TokenAccount>,
+    /// CHECK: PDA that owns the NFT's investment escrow; holds no data
+    #[account(seeds = [b"escrow_authority", nft.key().as_ref()], bump)]
+    pub escrow_authority: UncheckedAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = investor,
+        token::mint = investment_mint,
+        token::authority = escrow_authority,
+        seeds = [b"custodial_escrow", nft.key().as_ref(), investment_mint.key().as_ref()],
+        bump
+    )]
+    pub escrow_vault: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub investor: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/// The account context for withdrawing a custodial investment.
+#[derive(Accounts)]
+pub struct WithdrawCustodialInvestment<'info> {
+    #[account(mut)]
+    pub nft: Account<'info, NftAccount>,
+    #[account(
+        mut,
+        seeds = [b"investment", nft.key().as_ref(), investor.key().as_ref()],
+        bump,
+        has_one = investor @ CustomError::Unauthorized
+    )]
+    pub investment_account: Account<'info, CustodialInvestment>,
+    #[account(seeds = [b"dao_config"], bump)]
+    pub dao_config: Account<'info, DaoConfig>,
+    #[account(
+        mut,
+        constraint = investor_token_account.mint == dao_config.usdt_mint @ CustomError::InvalidInvestmentMint,
+        constraint = investor_token_account.owner == investor.key() @ CustomError::InvalidTokenOwner
+    )]
+    pub investor_token_account: Account<'info, TokenAccount>,
+    /// CHECK: PDA that owns the NFT's investment escrow; holds no data
+    #[account(seeds = [b"escrow_authority", nft.key().as_ref()], bump)]
+    pub escrow_authority: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"custodial_escrow", nft.key().as_ref(), dao_config.usdt_mint.as_ref()],
+        bump
+    )]
+    pub escrow_vault: Account<'info, TokenAccount>,
+    pub investor: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+/// The account context for buying fractional NFT shares.
+#[derive(Accounts)]
+pub struct BuyFractionalShares<'info> {
+    #[account(mut)]
+    pub nft: Account<'info, NftAccount>,
+    #[account(
+        mut,
+        seeds = [b"fractional", nft.key().as_ref()],
+        bump,
+        constraint = fractional_nft.parent_nft == nft.key() @ CustomError::InvalidFractionalAccount
+    )]
+    pub fractional_nft: Account<'info, FractionalNft>,
+    #[account(address = fractional_nft.mint @ CustomError::InvalidFractionalAccount)]
+    pub fractional_mint: Account<'info, Mint>,
+    /// CHECK: PDA that owns the fractional vault; holds no data
+    #[account(seeds = [b"fractional_authority", nft.key().as_ref()], bump)]
+    pub fractional_authority: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"fractional_vault", nft.key().as_ref()], bump)]
+    pub fractional_vault: Account<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = investor,
+        space = NonCustodialInvestment::LEN,
+        seeds = [b"investment", nft.key().as_ref(), investor.key().as_ref()],
+        bump
+    )]
+    pub investment_account: Account<'info, NonCustodialInvestment>,
+    #[account(
+        init_if_needed,
+        payer = investor,
+        token::mint = fractional_mint,
+        token::authority = fractional_authority,
+        seeds = [b"share_escrow", nft.key().as_ref(), investor.key().as_ref()],
+        bump
+    )]
+    pub share_escrow: Account<'info, TokenAccount>,
+    #[account(seeds = [b"dao_config"], bump)]
+    pub dao_config: Account<'info, DaoConfig>,
+    #[account(
+        mut,
+        seeds = [b"treasury_asset", dao_config.usdt_mint.as_ref()],
+        bump = treasury_asset.bump
+    )]
+    pub treasury_asset: Account<'info, TreasuryAsset>,
+    #[account(mut, address = treasury_asset.vault)]
+    pub treasury_vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = buyer_usdt_account.mint == dao_config.usdt_mint @ CustomError::InvalidInvestmentMint,
+        constraint = buyer_usdt_account.owner == investor.key() @ CustomError::InvalidTokenOwner
+    )]
+    pub buyer_usdt_account: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub investor: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/// The account context for depositing fractional shares into escrow.
+#[derive(Accounts)]
+pub struct DepositFractionalShares<'info> {
+    #[account(mut)]
+    pub nft: Account<'info, NftAccount>,
+    #[account(
+        seeds = [b"fractional", nft.key().as_ref()],
+        bump,
+        constraint = fractional_nft.parent_nft == nft.key() @ CustomError::InvalidFractionalAccount
+    )]
+    pub fractional_nft: Account<'info, FractionalNft>,
+    #[account(address = fractional_nft.mint @ CustomError::InvalidFractionalAccount)]
+    pub fractional_mint: Account<'info, Mint>,
+    /// CHECK: PDA that owns the fractional vault and share escrows; holds no data
+    #[account(seeds = [b"fractional_authority", nft.key().as_ref()], bump)]
+    pub fractional_authority: UncheckedAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = investor,
+        space = NonCustodialInvestment::LEN,
+        seeds = [b"investment", nft.key().as_ref(), investor.key().as_ref()],
+        bump
+    )]
+    pub investment_account: Account<'info, NonCustodialInvestment>,
+    #[account(
+        init_if_needed,
+        payer = investor,
+        token::mint = fractional_mint,
+        token::authority = fractional_authority,
+        seeds = [b"share_escrow", nft.key().as_ref(), investor.key().as_ref()],
+        bump
+    )]
+    pub share_escrow: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = investor_token_account.mint == fractional_mint.key() @ CustomError::InvalidFractionalAccount,
+        constraint = investor_token_account.owner == investor.key() @ CustomError::InvalidTokenOwner
+    )]
+    pub investor_token_account: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub investor: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/// The account context for withdrawing fractional shares from escrow.
+#[derive(Accounts)]
+pub struct WithdrawFractionalShares<'info> {
+    #[account(mut)]
+    pub nft: Account<'info, NftAccount>,
+    #[account(
+        seeds = [b"fractional", nft.key().as_ref()],
+        bump,
+        constraint = fractional_nft.parent_nft == nft.key() @ CustomError::InvalidFractionalAccount
+    )]
+    pub fractional_nft: Account<'info, FractionalNft>,
+    #[account(address = fractional_nft.mint @ CustomError::InvalidFractionalAccount)]
+    pub fractional_mint: Account<'info, Mint>,
+    /// CHECK: PDA that owns the fractional vault and share escrows; holds no data
+    #[account(seeds = [b"fractional_authority", nft.key().as_ref()], bump)]
+    pub fractional_authority: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"investment", nft.key().as_ref(), investor.key().as_ref()],
+        bump,
+        has_one = investor @ CustomError::Unauthorized
+    )]
+    pub investment_account: Account<'info, NonCustodialInvestment>,
+    #[account(mut, seeds = [b"share_escrow", nft.key().as_ref(), investor.key().as_ref()], bump)]
+    pub share_escrow: Account<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = investor,
+        associated_token::mint = fractional_mint,
+        associated_token::authority = investor,
+    )]
+    pub investor_token_account: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub investor: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+/// The account context for distributing passive income.
+#[derive(Accounts)]
+pub struct DistributeIncome<'info> {
+    #[account(mut)]
+    pub nft: Account<'info, NftAccount>,
+    #[account(mut, seeds = [b"rewards_config"], bump = rewards_config.bump)]
+    pub rewards_config: Account<'info, RewardsConfig>,
+    #[account(address = rewards_config.reward_vault)]
+    pub reward_vault: Account<'info, TokenAccount>,
+    #[account(address = rewards_config.reward_token_mint @ CustomError::InvalidRewardMint)]
+    pub reward_mint: Account<'info, Mint>,
+    #[account(seeds = [b"dao_config"], bump)]
+    pub dao_config: Account<'info, DaoConfig>,
+    #[account(address = dao_config.usdt_mint @ CustomError::InvalidInvestmentMint)]
+    pub usdt_mint: Account<'info, Mint>,
+    /// Required for non-custodial NFTs, to value their shares
+    #[account(seeds = [b"fractional", nft.key().as_ref()], bump)]
+    pub fractional_nft: Option<Account<'info, FractionalNft>>,
+}
+
+/// The account context for claiming passive income.
+#[derive(Accounts)]
+pub struct ClaimNftIncome<'info> {
+    pub nft: Account<'info, NftAccount>,
+    #[account(
+        mut,
+        seeds = [b"investment", nft.key().as_ref(), investor.key().as_ref()],
+        bump,
+        has_one = investor @ CustomError::Unauthorized
+    )]
+    pub investment: Account<'info, NonCustodialInvestment>,
+    #[account(mut, seeds = [b"rewards_config"], bump = rewards_config.bump)]
+    pub rewards_config: Account<'info, RewardsConfig>,
+    #[account(mut, address = rewards_config.reward_vault)]
+    pub reward_vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = investor_reward_account.mint == rewards_config.reward_token_mint @ CustomError::InvalidRewardMint,
+        constraint = investor_reward_account.owner == investor.key() @ CustomError::InvalidTokenOwner
+    )]
+    pub investor_reward_account: Account<'info, TokenAccount>,
+    pub investor: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+/// The account context for auto-staking custodial income.
+#[derive(Accounts)]
+pub struct ClaimStakingRewards<'info> {
+    pub nft: Account<'info, NftAccount>,
+    #[account(
+        mut,
+        seeds = [b"investment", nft.key().as_ref(), investor.key().as_ref()],
+        bump,
+        has_one = investor @ CustomError::Unauthorized
+    )]
+    pub investment: Account<'info, CustodialInvestment>,
+    #[account(mut, seeds = [b"rewards_config"], bump = rewards_config.bump)]
+    pub rewards_config: Account<'info, RewardsConfig>,
+    #[account(mut, address = rewards_config.reward_vault)]
+    pub reward_vault: Account<'info, TokenAccount>,
+    #[account(address = rewards_config.reward_token_mint @ CustomError::InvalidRewardMint)]
+    pub reward_mint: Account<'info, Mint>,
+    #[account(
+        init_if_needed,
+        payer = investor,
+        space = RewardStake::LEN,
+        seeds = [b"reward_stake", nft.key().as_ref(), investor.key().as_ref()],
+        bump
+    )]
+    pub reward_stake: Account<'info, RewardStake>,
+    #[account(
+        init_if_needed,
+        payer = investor,
+        token::mint = reward_mint,
+        token::authority = reward_stake,
+        seeds = [b"reward_stake_vault", reward_stake.key().as_ref()],
+        bump
+    )]
+    pub stake_vault: Account<'info, TokenAccount>,
+    // Proof the investor holds a card of this NFT: a `LoyaltyCard`, or a compressed card
+    pub loyalty_card: Option<Account<'info, LoyaltyCard>>,
+    pub card_tree: Option<Account<'info, CardTree>>,
+    /// CHECK: Concurrent Merkle tree registered in `card_tree`
+    #[account(owner = spl_account_compression::ID)]
+    pub merkle_tree: Option<UncheckedAccount<'info>>,
+    pub compression_program: Option<Program<'info, SplAccountCompression>>,
+    #[account(mut)]
+    pub investor: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/// The account context for staking reward tokens.
+#[derive(Accounts)]
+pub struct StakeRewards<'info> {
+    pub nft: Account<'info, NftAccount>,
+    #[account(seeds = [b"rewards_config"], bump = rewards_config.bump)]
+    pub rewards_config: Account<'info, RewardsConfig>,
+    #[account(address = rewards_config.reward_token_mint @ CustomError::InvalidRewardMint)]
+    pub reward_mint: Account<'info, Mint>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = RewardStake::LEN,
+        seeds = [b"reward_stake", nft.key().as_ref(), owner.key().as_ref()],
+        bump
+    )]
+    pub reward_stake: Account<'info, RewardStake>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        token::mint = reward_mint,
+        token::authority = reward_stake,
+        seeds = [b"reward_stake_vault", reward_stake.key().as_ref()],
+        bump
+    )]
+    pub stake_vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = owner_reward_account.mint == reward_mint.key() @ CustomError::InvalidRewardMint,
+        constraint = owner_reward_account.owner == owner.key() @ CustomError::InvalidTokenOwner
+    )]
+    pub owner_reward_account: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/// The account context for unstaking reward tokens.
+#[derive(Accounts)]
+pub struct UnstakeRewards<'info> {
+    pub nft: Account<'info, NftAccount>,
+    #[account(
+        mut,
+        seeds = [b"reward_stake", nft.key().as_ref(), owner.key().as_ref()],
+        bump = reward_stake.bump,
+        has_one = owner @ CustomError::Unauthorized
+    )]
+    pub reward_stake: Account<'info, RewardStake>,
+    #[account(mut, seeds = [b"reward_stake_vault", reward_stake.key().as_ref()], bump)]
+    pub stake_vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = owner_reward_account.mint == stake_vault.mint @ CustomError::InvalidRewardMint,
+        constraint = owner_reward_account.owner == owner.key() @ CustomError::InvalidTokenOwner
+    )]
+    pub owner_reward_account: Account<'info, TokenAccount>,
+    pub owner: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+/// The account context for migrating an unversioned NFT account.
+#[derive(Accounts)]
+pub struct MigrateNftAccount<'info> {
+    /// CHECK: Decoded manually; unversioned layouts do not deserialize as `NftAccount`
+    #[account(mut, owner = crate::ID)]
+    pub nft: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// The account context for migrating the unversioned DAO config.
+#[derive(Accounts)]
+pub struct MigrateDaoConfig<'info> {
+    /// CHECK: Decoded manually; unversioned layouts do not deserialize as `DaoConfig`
+    #[account(mut, seeds = [b"dao_config"], bump, owner = crate::ID)]
+    pub dao_config: UncheckedAccount<'info>,
+    pub rac_mint: Account<'info, Mint>,
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()) @ CustomError::Unauthorized)]
+    pub program: Program<'info, crate::program::SolanaDaoNftContract>,
+    #[account(constraint = program_data.upgrade_authority_address == Some(authority.key()) @ CustomError::Unauthorized)]
+    pub program_data: Account<'info, ProgramData>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// The account context for migrating an unversioned proposal.
+#[derive(Accounts)]
+pub struct MigrateProposal<'info> {
+    /// CHECK: Decoded manually; unversioned layouts do not deserialize as `Proposal`
+    #[account(mut, owner = crate::ID)]
+    pub proposal: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// The account context for adding the `version` byte to any other fixed-size account.
+#[derive(Accounts)]
+pub struct MigrateAccountVersion<'info> {
+    /// CHECK: Identified by its discriminator and length in `migration::versioned_account_len`
+    #[account(mut, owner = crate::ID)]
+    pub account: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// The account context for migrating the original rewards configuration.
+/// That layout had no reward vault, so it is created here.
+#[derive(Accounts)]
+pub struct MigrateRewardsConfig<'info> {
+    /// CHECK: Decoded manually; the original layout does not deserialize as `RewardsConfig`
+    #[account(mut, owner = crate::ID, seeds = [b"rewards_config"], bump)]
+    pub rewards_config: UncheckedAccount<'info>,
+    pub reward_token_mint: Account<'info, Mint>,
+    #[account(
+        init,
+        payer = admin,
+        token::mint = reward_token_mint,
+        token::authority = rewards_config,
+        seeds = [b"reward_vault"],
+        bump
+    )]
+    pub reward_vault: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/// The account context for registering a merchant.
+#[derive(Accounts)]
+pub struct RegisterMerchant<'info> {
+    #[account(seeds = [b"rewards_config"], bump = rewards_config.bump, has_one = admin_authority @ CustomError::UnauthorizedAdmin)]
+    pub rewards_config: Account<'info, RewardsConfig>,
+    #[account(
+        init,
+        payer = admin_authority,
+        space = Merchant::LEN,
+        seeds = [b"merchant", merchant_authority.key().as_ref()],
+        bump
+    )]
+    pub merchant: Account<'info, Merchant>,
+    /// CHECK: The merchant's signing key; only stored
+    pub merchant_authority: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub admin_authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
 }
 
-// Account contexts for existing functions remain the same...
+/// The account context for enabling or disabling a merchant.
+#[derive(Accounts)]
+pub struct SetMerchantActive<'info> {
+    #[account(seeds = [b"rewards_config"], bump = rewards_config.bump, has_one = admin_authority @ CustomError::UnauthorizedAdmin)]
+    pub rewards_config: Account<'info, RewardsConfig>,
+    #[account(mut, seeds = [b"merchant", merchant.authority.as_ref()], bump = merchant.bump)]
+    pub merchant: Account<'info, Merchant>,
+    pub admin_authority: Signer<'info>,
+}
 
-/// The account context for the `create_rac_mint` instruction.
+/// The account context for recording a customer purchase.
 #[derive(Accounts)]
-pub struct CreateRacMint<'info> {
+#[instruction(receipt_id: [u8; 32])]
+pub struct RecordSpend<'info> {
+    #[account(
+        mut,
+        seeds = [b"merchant", merchant_authority.key().as_ref()],
+        bump = merchant.bump,
+        constraint = merchant.authority == merchant_authority.key() @ CustomError::Unauthorized
+    )]
+    pub merchant: Box<Account<'info, Merchant>>,
+    #[account(mut)]
+    pub merchant_authority: Signer<'info>,
+    /// CHECK: The customer's wallet; must hold a card of `nft`
+    pub customer: UncheckedAccount<'info>,
+    pub nft: Box<Account<'info, NftAccount>>,
+    // Proof the customer holds a card of this NFT: a `LoyaltyCard`, or a compressed card
+    pub loyalty_card: Option<Account<'info, LoyaltyCard>>,
+    pub card_tree: Option<Account<'info, CardTree>>,
+    /// CHECK: Concurrent Merkle tree registered in `card_tree`
+    #[account(owner = spl_account_compression::ID)]
+    pub merkle_tree: Option<UncheckedAccount<'info>>,
+    pub compression_program: Option<Program<'info, SplAccountCompression>>,
     #[account(
         init,
-        payer = admin,
-        space = DaoConfig::LEN,
-        seeds = [b"dao_config"],
+        payer = merchant_authority,
+        space = SpendReceipt::LEN,
+        seeds = [b"receipt", merchant.key().as_ref(), receipt_id.as_ref()],
         bump
     )]
-    pub dao_config: Account<'info, DaoConfig>,
+    pub receipt: Box<Account<'info, SpendReceipt>>,
+    #[account(
+        init_if_needed,
+        payer = merchant_authority,
+        space = SpendTracker::LEN,
+        seeds = [b"spend_tracker", customer.key().as_ref()],
+        bump
+    )]
+    pub spend_tracker: Box<Account<'info, SpendTracker>>,
+    #[account(seeds = [b"dao_config"], bump)]
+    pub dao_config: Box<Account<'info, DaoConfig>>,
+    #[account(address = dao_config.usdt_mint @ CustomError::InvalidInvestmentMint)]
+    pub usdt_mint: Box<Account<'info, Mint>>,
+    #[account(seeds = [b"rewards_config"], bump = rewards_config.bump)]
+    pub rewards_config: Box<Account<'info, RewardsConfig>>,
+    #[account(mut, address = rewards_config.reward_vault)]
+    pub reward_vault: Box<Account<'info, TokenAccount>>,
+    #[account(address = rewards_config.reward_token_mint @ CustomError::InvalidRewardMint)]
+    pub reward_mint: Box<Account<'info, Mint>>,
+    #[account(
+        init_if_needed,
+        payer = merchant_authority,
+        associated_token::mint = reward_mint,
+        associated_token::authority = customer,
+    )]
+    pub customer_reward_account: Box<Account<'info, TokenAccount>>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+/// The account context for creating a loyalty card collection.
+#[derive(Accounts)]
+#[instruction(collection: Collection)]
+pub struct InitializeCollection<'info> {
+    #[account(seeds = [b"dao_config"], bump, has_one = admin_authority @ CustomError::UnauthorizedAdmin)]
+    pub dao_config: Box<Account<'info, DaoConfig>>,
     #[account(
         init,
-        payer = admin,
-        mint::decimals = 9,
-        mint::authority = admin,
+        payer = admin_authority,
+        space = CardCollection::LEN,
+        seeds = [b"card_collection", collection.as_str().as_bytes()],
+        bump
     )]
-    pub rac_mint: Account<'info, Mint>,
+    pub card_collection: Box<Account<'info, CardCollection>>,
     #[account(
         init,
-        payer = admin,
-        associated_token::mint = rac_mint,
-        associated_token::authority = admin,
+        payer = admin_authority,
+        mint::decimals = 0,
+        mint::authority = card_authority,
+        mint::freeze_authority = card_authority,
+        seeds = [b"collection_mint", collection.as_str().as_bytes()],
+        bump
     )]
-    pub dao_vault: Account<'info, TokenAccount>,
+    pub collection_mint: Box<Account<'info, Mint>>,
+    #[account(
+        init,
+        payer = admin_authority,
+        associated_token::mint = collection_mint,
+        associated_token::authority = card_authority,
+    )]
+    pub collection_token_account: Box<Account<'info, TokenAccount>>,
+    /// CHECK: Metaplex metadata PDA, created by the token metadata program
+    #[account(
+        mut,
+        seeds = [b"metadata", token_metadata_program.key().as_ref(), collection_mint.key().as_ref()],
+        bump,
+        seeds::program = token_metadata_program.key()
+    )]
+    pub collection_metadata: UncheckedAccount<'info>,
+    /// CHECK: Metaplex master edition PDA, created by the token metadata program
+    #[account(
+        mut,
+        seeds = [b"metadata", token_metadata_program.key().as_ref(), collection_mint.key().as_ref(), b"edition"],
+        bump,
+        seeds::program = token_metadata_program.key()
+    )]
+    pub collection_master_edition: UncheckedAccount<'info>,
+    /// CHECK: PDA that controls card mints and metadata. Holds no data.
+    #[account(seeds = [b"card_authority"], bump)]
+    pub card_authority: UncheckedAccount<'info>,
     #[account(mut)]
-    pub admin: Signer<'info>,
+    pub admin_authority: Signer<'info>,
     pub token_program: Program<'info, Token>,
     pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_metadata_program: Program<'info, Metadata>,
     pub system_program: Program<'info, System>,
     pub rent: Sysvar<'info, Rent>,
 }
 
-/// The account context for initializing rewards configuration.
+/// The account context for minting a loyalty card NFT.
 #[derive(Accounts)]
-pub struct InitializeRewardsConfig<'info> {
+pub struct MintLoyaltyCard<'info> {
+    #[account(mut)]
+    pub nft: Box<Account<'info, NftAccount>>,
+    #[account(
+        mut,
+        seeds = [b"card_collection", nft.collection.as_str().as_bytes()],
+        bump = card_collection.bump
+    )]
+    pub card_collection: Box<Account<'info, CardCollection>>,
+    #[account(address = card_collection.mint)]
+    pub collection_mint: Box<Account<'info, Mint>>,
+    /// CHECK: Metaplex metadata PDA of the collection
+    #[account(
+        mut,
+        seeds = [b"metadata", token_metadata_program.key().as_ref(), collection_mint.key().as_ref()],
+        bump,
+        seeds::program = token_metadata_program.key()
+    )]
+    pub collection_metadata: UncheckedAccount<'info>,
+    /// CHECK: Metaplex master edition PDA of the collection
+    #[account(
+        seeds = [b"metadata", token_metadata_program.key().as_ref(), collection_mint.key().as_ref(), b"edition"],
+        bump,
+        seeds::program = token_metadata_program.key()
+    )]
+    pub collection_master_edition: UncheckedAccount<'info>,
     #[account(
         init,
-        payer = admin,
-        space = RewardsConfig::LEN,
-        seeds = [b"rewards_config"],
+        payer = owner,
+        mint::decimals = 0,
+        mint::authority = card_authority,
+        mint::freeze_authority = card_authority
+    )]
+    pub card_mint: Box<Account<'info, Mint>>,
+    #[account(
+        init,
+        payer = owner,
+        associated_token::mint = card_mint,
+        associated_token::authority = owner,
+    )]
+    pub owner_card_account: Box<Account<'info, TokenAccount>>,
+    /// CHECK: Metaplex metadata PDA, created by the token metadata program
+    #[account(
+        mut,
+        seeds = [b"metadata", token_metadata_program.key().as_ref(), card_mint.key().as_ref()],
+        bump,
+        seeds::program = token_metadata_program.key()
+    )]
+    pub card_metadata: UncheckedAccount<'info>,
+    /// CHECK: Metaplex master edition PDA, created by the token metadata program
+    #[account(
+        mut,
+        seeds = [b"metadata", token_metadata_program.key().as_ref(), card_mint.key().as_ref(), b"edition"],
+        bump,
+        seeds::program = token_metadata_program.key()
+    )]
+    pub card_master_edition: UncheckedAccount<'info>,
+    #[account(
+        init,
+        payer = owner,
+        space = LoyaltyCard::LEN,
+        seeds = [b"loyalty_card", card_mint.key().as_ref()],
         bump
     )]
-    pub rewards_config: Account<'info, RewardsConfig>,
-    #[account(mut)]
-    pub reward_token_mint: Account<'info, Mint>,
+    pub loyalty_card: Box<Account<'info, LoyaltyCard>>,
+    /// CHECK: PDA that controls card mints and metadata. Holds no data.
+    #[account(seeds = [b"card_authority"], bump)]
+    pub card_authority: UncheckedAccount<'info>,
+    #[account(seeds = [b"dao_config"], bump)]
+    pub dao_config: Box<Account<'info, DaoConfig>>,
+    #[account(
+        mut,
+        seeds = [b"treasury_asset", dao_config.usdt_mint.as_ref()],
+        bump = treasury_asset.bump
+    )]
+    pub treasury_asset: Box<Account<'info, TreasuryAsset>>,
+    #[account(mut, address = treasury_asset.vault)]
+    pub treasury_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = owner_usdt_account.mint == dao_config.usdt_mint @ CustomError::InvalidInvestmentMint,
+        constraint = owner_usdt_account.owner == owner.key() @ CustomError::InvalidTokenOwner
+    )]
+    pub owner_usdt_account: Box<Account<'info, TokenAccount>>,
     #[account(mut)]
-    pub admin: Signer<'info>,
-    /// CHECK: This is the program ID
-    pub program_id: UncheckedAccount<'info>,
+    pub owner: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_metadata_program: Program<'info, Metadata>,
     pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
 }
 
-/// The account context for the `create_nft` instruction.
+/// The account context for upgrading a loyalty card to a higher tier.
 #[derive(Accounts)]
-#[instruction(collection_name: String, nft_name: String, display_name: String, symbol: String, is_custodial: bool)]
-pub struct CreateNft<'info> {
+pub struct UpgradeCard<'info> {
+    #[account(
+        mut,
+        seeds = [b"loyalty_card", old_card.mint.as_ref()],
+        bump = old_card.bump,
+        constraint = old_card.owner == owner.key() @ CustomError::Unauthorized
+    )]
+    pub old_card: Box<Account<'info, LoyaltyCard>>,
+    #[account(address = old_card.nft)]
+    pub old_nft: Box<Account<'info, NftAccount>>,
+    #[account(mut, address = old_card.mint)]
+    pub old_card_mint: Box<Account<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = old_card_token_account.mint == old_card.mint @ CustomError::InvalidCardAccount,
+        constraint = old_card_token_account.owner == owner.key() @ CustomError::InvalidTokenOwner,
+        constraint = old_card_token_account.amount == 1 @ CustomError::InvalidCardAccount
+    )]
+    pub old_card_token_account: Box<Account<'info, TokenAccount>>,
+    /// CHECK: Metaplex metadata PDA of the old card
+    #[account(
+        mut,
+        seeds = [b"metadata", token_metadata_program.key().as_ref(), old_card.mint.as_ref()],
+        bump,
+        seeds::program = token_metadata_program.key()
+    )]
+    pub old_card_metadata: UncheckedAccount<'info>,
+    /// CHECK: Metaplex master edition PDA of the old card
+    #[account(
+        mut,
+        seeds = [b"metadata", token_metadata_program.key().as_ref(), old_card.mint.as_ref(), b"edition"],
+        bump,
+        seeds::program = token_metadata_program.key()
+    )]
+    pub old_card_master_edition: UncheckedAccount<'info>,
+    /// CHECK: Collection mint PDA of the old card's collection
+    #[account(seeds = [b"collection_mint", old_nft.collection.as_str().as_bytes()], bump)]
+    pub old_collection_mint: UncheckedAccount<'info>,
+    /// CHECK: Metaplex metadata PDA of the old card's collection, updated by the burn
+    #[account(
+        mut,
+        seeds = [b"metadata", token_metadata_program.key().as_ref(), old_collection_mint.key().as_ref()],
+        bump,
+        seeds::program = token_metadata_program.key()
+    )]
+    pub old_collection_metadata: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub new_nft: Box<Account<'info, NftAccount>>,
+    #[account(
+        mut,
+        seeds = [b"card_collection", new_nft.collection.as_str().as_bytes()],
+        bump = card_collection.bump
+    )]
+    pub card_collection: Box<Account<'info, CardCollection>>,
+    #[account(address = card_collection.mint)]
+    pub collection_mint: Box<Account<'info, Mint>>,
+    /// CHECK: Metaplex metadata PDA of the new card's collection
+    #[account(
+        mut,
+        seeds = [b"metadata", token_metadata_program.key().as_ref(), collection_mint.key().as_ref()],
+        bump,
+        seeds::program = token_metadata_program.key()
+    )]
+    pub collection_metadata: UncheckedAccount<'info>,
+    /// CHECK: Metaplex master edition PDA of the new card's collection
+    #[account(
+        seeds = [b"metadata", token_metadata_program.key().as_ref(), collection_mint.key().as_ref(), b"edition"],
+        bump,
+        seeds::program = token_metadata_program.key()
+    )]
+    pub collection_master_edition: UncheckedAccount<'info>,
     #[account(
         init,
-        payer = admin,
-        space = NftAccount::LEN,
-        seeds = [b"nft", nft_name.as_bytes(), symbol.as_bytes()],
-        bump
+        payer = owner,
+        mint::decimals = 0,
+        mint::authority = card_authority,
+        mint::freeze_authority = card_authority
     )]
-    pub nft: Account<'info, NftAccount>,
+    pub card_mint: Box<Account<'info, Mint>>,
     #[account(
-        init_if_needed,
-        payer = admin,
-        space = FractionalNft::LEN,
-        seeds = [b"fractional", nft.key().as_ref()],
+        init,
+        payer = owner,
+        associated_token::mint = card_mint,
+        associated_token::authority = owner,
+    )]
+    pub owner_card_account: Box<Account<'info, TokenAccount>>,
+    /// CHECK: Metaplex metadata PDA, created by the token metadata program
+    #[account(
+        mut,
+        seeds = [b"metadata", token_metadata_program.key().as_ref(), card_mint.key().as_ref()],
         bump,
-        constraint = !is_custodial @ CustomError::AccountShouldNotBeUsed
+        seeds::program = token_metadata_program.key()
     )]
-    pub fractional_nft: Option<Account<'info, FractionalNft>>,
+    pub card_metadata: UncheckedAccount<'info>,
+    /// CHECK: Metaplex master edition PDA, created by the token metadata program
     #[account(
-        init_if_needed,
-        payer = admin,
-        mint::decimals = 6,
-        mint::authority = admin,
-        constraint = !is_custodial @ CustomError::AccountShouldNotBeUsed
+        mut,
+        seeds = [b"metadata", token_metadata_program.key().as_ref(), card_mint.key().as_ref(), b"edition"],
+        bump,
+        seeds::program = token_metadata_program.key()
     )]
-    pub fractional_mint: Option<Account<'info, Mint>>,
+    pub card_master_edition: UncheckedAccount<'info>,
     #[account(
-        init_if_needed,
-        payer = admin,
-        associated_token::mint = fractional_mint.as_ref().unwrap(),
-        associated_token::authority = admin,
-        constraint = !is_custodial @ CustomError::AccountShouldNotBeUsed
+        init,
+        payer = owner,
+        space = LoyaltyCard::LEN,
+        seeds = [b"loyalty_card", card_mint.key().as_ref()],
+        bump
     )]
-    pub fractional_vault: Option<Account<'info, TokenAccount>>,
+    pub new_card: Box<Account<'info, LoyaltyCard>>,
+    /// CHECK: PDA that controls card mints and metadata. Holds no data.
+    #[account(seeds = [b"card_authority"], bump)]
+    pub card_authority: UncheckedAccount<'info>,
+    #[account(seeds = [b"dao_config"], bump)]
+    pub dao_config: Box<Account<'info, DaoConfig>>,
+    #[account(address = dao_config.usdt_mint @ CustomError::InvalidInvestmentMint)]
+    pub usdt_mint: Box<Account<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [b"treasury_asset", dao_config.usdt_mint.as_ref()],
+        bump = treasury_asset.bump
+    )]
+    pub treasury_asset: Box<Account<'info, TreasuryAsset>>,
+    #[account(mut, address = treasury_asset.vault)]
+    pub treasury_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = owner_usdt_account.mint == dao_config.usdt_mint @ CustomError::InvalidInvestmentMint,
+        constraint = owner_usdt_account.owner == owner.key() @ CustomError::InvalidTokenOwner
+    )]
+    pub owner_usdt_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        seeds = [b"rewards_config"],
+        bump = rewards_config.bump,
+        constraint = rewards_config.reward_token_mint == dao_config.rac_mint @ CustomError::InvalidRewardMint
+    )]
+    pub rewards_config: Box<Account<'info, RewardsConfig>>,
+    #[account(mut, address = rewards_config.reward_vault)]
+    pub reward_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = owner_rac_account.mint == dao_config.rac_mint @ CustomError::InvalidRacMint,
+        constraint = owner_rac_account.owner == owner.key() @ CustomError::InvalidTokenOwner
+    )]
+    pub owner_rac_account: Box<Account<'info, TokenAccount>>,
     #[account(mut)]
-    pub admin: Signer<'info>,
+    pub owner: Signer<'info>,
     pub token_program: Program<'info, Token>,
     pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_metadata_program: Program<'info, Metadata>,
     pub system_program: Program<'info, System>,
     pub rent: Sysvar<'info, Rent>,
 }
 
-/// The account context for the `update_nft` instruction.
+/// The account context for transferring a non-custodial loyalty card.
 #[derive(Accounts)]
-#[instruction(collection_name: String, nft_name: String, display_name: String, symbol: String)]
-pub struct UpdateNft<'info> {
+pub struct TransferCard<'info> {
+    #[account(
+        mut,
+        seeds = [b"loyalty_card", loyalty_card.mint.as_ref()],
+        bump = loyalty_card.bump,
+        constraint = loyalty_card.owner == owner.key() @ CustomError::Unauthorized
+    )]
+    pub loyalty_card: Box<Account<'info, LoyaltyCard>>,
+    #[account(address = loyalty_card.nft)]
+    pub nft: Box<Account<'info, NftAccount>>,
+    #[account(address = loyalty_card.mint)]
+    pub card_mint: Box<Account<'info, Mint>>,
     #[account(
         mut,
-        seeds = [b"nft", nft_name.as_bytes(), symbol.as_bytes()],
+        constraint = owner_card_account.mint == loyalty_card.mint @ CustomError::InvalidCardAccount,
+        constraint = owner_card_account.owner == owner.key() @ CustomError::InvalidTokenOwner,
+        constraint = owner_card_account.amount == 1 @ CustomError::InvalidCardAccount
+    )]
+    pub owner_card_account: Box<Account<'info, TokenAccount>>,
+    /// Signs to delegate the received card to `card_authority`, which freezes it again.
+    pub recipient: Signer<'info>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        associated_token::mint = card_mint,
+        associated_token::authority = recipient,
+    )]
+    pub recipient_card_account: Box<Account<'info, TokenAccount>>,
+    /// CHECK: Metaplex master edition PDA of the card
+    #[account(
+        seeds = [b"metadata", token_metadata_program.key().as_ref(), loyalty_card.mint.as_ref(), b"edition"],
         bump,
-        constraint = nft.admin == admin.key() @ CustomError::Unauthorized
+        seeds::program = token_metadata_program.key()
     )]
-    pub nft: Account<'info, NftAccount>,
+    pub card_master_edition: UncheckedAccount<'info>,
+    /// CHECK: PDA that controls card mints and metadata. Holds no data.
+    #[account(seeds = [b"card_authority"], bump)]
+    pub card_authority: UncheckedAccount<'info>,
     #[account(mut)]
+    pub owner: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub token_metadata_program: Program<'info, Metadata>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+/// The account context for setting an NFT's evolved metadata URI.
+#[derive(Accounts)]
+pub struct SetEvolvedUri<'info> {
+    #[account(mut, constraint = nft.admin == admin.key() @ CustomError::Unauthorized)]
+    pub nft: Account<'info, NftAccount>,
     pub admin: Signer<'info>,
 }
 
-/// The account context for a user investing in a custodial NFT.
+/// The account context for evolving a loyalty card.
 #[derive(Accounts)]
-#[instruction(investment_amount: u64)]
-pub struct InvestInCustodialNft<'info> {
+pub struct EvolveCard<'info> {
+    #[account(
+        mut,
+        seeds = [b"loyalty_card", loyalty_card.mint.as_ref()],
+        bump = loyalty_card.bump,
+        constraint = loyalty_card.owner == owner.key() @ CustomError::Unauthorized
+    )]
+    pub loyalty_card: Box<Account<'info, LoyaltyCard>>,
+    #[account(address = loyalty_card.nft)]
+    pub nft: Box<Account<'info, NftAccount>>,
+    #[account(seeds = [b"ve_lock", owner.key().as_ref()], bump)]
+    pub ve_lock: Option<Account<'info, VeLock>>,
+    #[account(seeds = [b"reward_stake", nft.key().as_ref(), owner.key().as_ref()], bump)]
+    pub reward_stake: Option<Account<'info, RewardStake>>,
+    #[account(seeds = [b"investment", nft.key().as_ref(), owner.key().as_ref()], bump)]
+    pub custodial_investment: Option<Account<'info, CustodialInvestment>>,
+    #[account(seeds = [b"investment", nft.key().as_ref(), owner.key().as_ref()], bump)]
+    pub fractional_investment: Option<Account<'info, NonCustodialInvestment>>,
+    #[account(seeds = [b"fractional", nft.key().as_ref()], bump)]
+    pub fractional_nft: Option<Account<'info, FractionalNft>>,
+    #[account(seeds = [b"dao_config"], bump)]
+    pub dao_config: Box<Account<'info, DaoConfig>>,
+    #[account(address = dao_config.usdt_mint @ CustomError::InvalidInvestmentMint)]
+    pub usdt_mint: Box<Account<'info, Mint>>,
+    #[account(seeds = [b"card_collection", nft.collection.as_str().as_bytes()], bump = card_collection.bump)]
+    pub card_collection: Box<Account<'info, CardCollection>>,
+    /// CHECK: Metaplex metadata PDA of the card
+    #[account(
+        mut,
+        seeds = [b"metadata", token_metadata_program.key().as_ref(), loyalty_card.mint.as_ref()],
+        bump,
+        seeds::program = token_metadata_program.key()
+    )]
+    pub card_metadata: UncheckedAccount<'info>,
+    /// CHECK: PDA that controls card mints and metadata. Holds no data.
+    #[account(seeds = [b"card_authority"], bump)]
+    pub card_authority: UncheckedAccount<'info>,
+    pub owner: Signer<'info>,
+    pub token_metadata_program: Program<'info, Metadata>,
+}
+
+/// The account context for querying a card's evolution eligibility.
+#[derive(Accounts)]
+pub struct GetEvolutionEligibility<'info> {
+    #[account(seeds = [b"loyalty_card", loyalty_card.mint.as_ref()], bump = loyalty_card.bump)]
+    pub loyalty_card: Account<'info, LoyaltyCard>,
+    #[account(address = loyalty_card.nft)]
+    pub nft: Account<'info, NftAccount>,
+    #[account(seeds = [b"ve_lock", loyalty_card.owner.as_ref()], bump)]
+    pub ve_lock: Option<Account<'info, VeLock>>,
+    #[account(seeds = [b"reward_stake", nft.key().as_ref(), loyalty_card.owner.as_ref()], bump)]
+    pub reward_stake: Option<Account<'info, RewardStake>>,
+    #[account(seeds = [b"investment", nft.key().as_ref(), loyalty_card.owner.as_ref()], bump)]
+    pub custodial_investment: Option<Account<'info, CustodialInvestment>>,
+    #[account(seeds = [b"investment", nft.key().as_ref(), loyalty_card.owner.as_ref()], bump)]
+    pub fractional_investment: Option<Account<'info, NonCustodialInvestment>>,
+    #[account(seeds = [b"fractional", nft.key().as_ref()], bump)]
+    pub fractional_nft: Option<Account<'info, FractionalNft>>,
+    #[account(seeds = [b"dao_config"], bump)]
+    pub dao_config: Box<Account<'info, DaoConfig>>,
+    #[account(address = dao_config.usdt_mint @ CustomError::InvalidInvestmentMint)]
+    pub usdt_mint: Box<Account<'info, Mint>>,
+}
+
+/// The account context for changing an NFT's mint cap.
+#[derive(Accounts)]
+pub struct SetMintQuantity<'info> {
     #[account(mut)]
     pub nft: Account<'info, NftAccount>,
+    #[account(seeds = [b"dao_authority"], bump)]
+    pub dao_authority: Signer<'info>,
+}
+
+/// The account context for registering a compressed card tree.
+#[derive(Accounts)]
+pub struct CreateCardTree<'info> {
+    #[account(seeds = [b"dao_config"], bump, has_one = admin_authority @ CustomError::UnauthorizedAdmin)]
+    pub dao_config: Box<Account<'info, DaoConfig>>,
+    pub nft: Box<Account<'info, NftAccount>>,
+    #[account(seeds = [b"card_collection", nft.collection.as_str().as_bytes()], bump = card_collection.bump)]
+    pub card_collection: Box<Account<'info, CardCollection>>,
     #[account(
-        // FIX: Allow multiple investments from the same user
-        init_if_needed,
-        payer = investor,
-        space = CustodialInvestment::LEN,
-        seeds = [b"investment", nft.key().as_ref(), investor.key().as_ref()],
+        init,
+        payer = admin_authority,
+        space = CardTree::LEN,
+        seeds = [b"card_tree", merkle_tree.key().as_ref()],
         bump
     )]
-    pub investment_account: Account<'info, CustodialInvestment>,
+    pub card_tree: Box<Account<'info, CardTree>>,
+    /// CHECK: Pre-allocated concurrent Merkle tree, initialized by the compression program
+    #[account(mut, owner = spl_account_compression::ID)]
+    pub merkle_tree: UncheckedAccount<'info>,
+    /// CHECK: Bubblegum tree config PDA, created by Bubblegum
+    #[account(
+        mut,
+        seeds = [merkle_tree.key().as_ref()],
+        bump,
+        seeds::program = mpl_bubblegum::ID
+    )]
+    pub tree_config: UncheckedAccount<'info>,
+    /// CHECK: PDA that creates and owns card trees. Holds no data.
+    #[account(seeds = [b"card_tree_authority"], bump)]
+    pub tree_authority: UncheckedAccount<'info>,
     #[account(mut)]
-    pub investor: Signer<'info>,
+    pub admin_authority: Signer<'info>,
+    /// CHECK: Bubblegum program
+    #[account(address = mpl_bubblegum::ID)]
+    pub bubblegum_program: UncheckedAccount<'info>,
+    pub log_wrapper: Program<'info, Noop>,
+    pub compression_program: Program<'info, SplAccountCompression>,
     pub system_program: Program<'info, System>,
 }
 
-/// The account context for a user investing in a non-custodial NFT.
+/// The account context for minting a compressed loyalty card.
 #[derive(Accounts)]
-#[instruction(investment_amount: u64)]
-pub struct InvestInNonCustodialNft<'info> {
+pub struct MintCompressedCard<'info> {
     #[account(mut)]
-    pub nft: Account<'info, NftAccount>,
-    #[account(mut, seeds = [b"fractional", nft.key().as_ref()], bump)]
-    pub fractional_nft: Account<'info, FractionalNft>,
+    pub nft: Box<Account<'info, NftAccount>>,
     #[account(
-        // FIX: Allow multiple investments from the same user
-        init_if_needed,
-        payer = investor,
-        space = NonCustodialInvestment::LEN,
-        seeds = [b"investment", nft.key().as_ref(), investor.key().as_ref()],
-        bump
+        mut,
+        seeds = [b"card_tree", merkle_tree.key().as_ref()],
+        bump = card_tree.bump,
+        constraint = card_tree.nft == nft.key() @ CustomError::InvalidCardTree
     )]
-    pub investment_account: Account<'info, NonCustodialInvestment>,
-    #[account(mut)]
-    pub fractional_mint: Account<'info, Mint>,
+    pub card_tree: Box<Account<'info, CardTree>>,
     #[account(
-        init_if_needed,
-        payer = investor,
-        associated_token::mint = fractional_mint,
-        associated_token::authority = investor,
+        mut,
+        seeds = [b"card_collection", nft.collection.as_str().as_bytes()],
+        bump = card_collection.bump
     )]
-    pub investor_token_account: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub admin: Signer<'info>,
+    pub card_collection: Box<Account<'info, CardCollection>>,
+    #[account(address = card_collection.mint)]
+    pub collection_mint: Box<Account<'info, Mint>>,
+    /// CHECK: Metaplex metadata PDA of the collection
+    #[account(
+        mut,
+        seeds = [b"metadata", token_metadata_program.key().as_ref(), collection_mint.key().as_ref()],
+        bump,
+        seeds::program = token_metadata_program.key()
+    )]
+    pub collection_metadata: UncheckedAccount<'info>,
+    /// CHECK: Metaplex master edition PDA of the collection
+    #[account(
+        seeds = [b"metadata", token_metadata_program.key().as_ref(), collection_mint.key().as_ref(), b"edition"],
+        bump,
+        seeds::program = token_metadata_program.key()
+    )]
+    pub collection_master_edition: UncheckedAccount<'info>,
+    /// CHECK: Concurrent Merkle tree registered in `card_tree`
+    #[account(mut, owner = spl_account_compression::ID)]
+    pub merkle_tree: UncheckedAccount<'info>,
+    /// CHECK: Bubblegum tree config PDA
+    #[account(
+        mut,
+        seeds = [merkle_tree.key().as_ref()],
+        bump,
+        seeds::program = mpl_bubblegum::ID
+    )]
+    pub tree_config: UncheckedAccount<'info>,
+    /// CHECK: PDA that creates and owns card trees. Holds no data.
+    #[account(seeds = [b"card_tree_authority"], bump)]
+    pub tree_authority: UncheckedAccount<'info>,
+    /// CHECK: PDA that controls card mints and metadata. Holds no data.
+    #[account(seeds = [b"card_authority"], bump)]
+    pub card_authority: UncheckedAccount<'info>,
+    /// CHECK: Bubblegum's collection CPI signer PDA
+    #[account(
+        seeds = [b"collection_cpi"],
+        bump,
+        seeds::program = mpl_bubblegum::ID
+    )]
+    pub bubblegum_signer: UncheckedAccount<'info>,
+    /// CHECK: PDA that owns the holder's compressed cards. Holds no data.
+    #[account(seeds = [b"card_custody", owner.key().as_ref()], bump)]
+    pub card_custody: UncheckedAccount<'info>,
     #[account(mut)]
-    pub investor: Signer<'info>,
-    pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub owner: Signer<'info>,
+    /// CHECK: Bubblegum program
+    #[account(address = mpl_bubblegum::ID)]
+    pub bubblegum_program: UncheckedAccount<'info>,
+    pub log_wrapper: Program<'info, Noop>,
+    pub compression_program: Program<'info, SplAccountCompression>,
+    pub token_metadata_program: Program<'info, Metadata>,
     pub system_program: Program<'info, System>,
-    pub rent: Sysvar<'info, Rent>,
 }
 
-/// The account context for distributing passive income.
+/// The account context for verifying a compressed card holder.
 #[derive(Accounts)]
-pub struct DistributeIncome<'info> {
-    #[account(mut)]
-    pub nft: Account<'info, NftAccount>,
-    #[account(mut)]
-    pub admin: Signer<'info>,
-    pub token_program: Program<'info, Token>,
+pub struct VerifyCompressedCard<'info> {
+    #[account(seeds = [b"card_tree", merkle_tree.key().as_ref()], bump = card_tree.bump)]
+    pub card_tree: Box<Account<'info, CardTree>>,
+    /// CHECK: Concurrent Merkle tree registered in `card_tree`
+    #[account(owner = spl_account_compression::ID)]
+    pub merkle_tree: UncheckedAccount<'info>,
+    pub holder: Signer<'info>,
+    pub compression_program: Program<'info, SplAccountCompression>,
 }
 
 /// The account context for updating the passive income rate.
@@ -695,12 +5619,17 @@ pub struct CreateProposal<'info> {
         init,
         payer = proposer,
         space = Proposal::LEN,
-        seeds = [b"proposal", dao_config.proposal_count.to_le_bytes().as_ref()],
+        seeds = [b"proposal", (dao_config.proposal_count + 1).to_le_bytes().as_ref()],
         bump
     )]
     pub proposal: Account<'info, Proposal>,
-    #[account(mut)]
+    #[account(mut, seeds = [b"dao_config"], bump)]
     pub dao_config: Account<'info, DaoConfig>,
+    #[account(
+        constraint = proposer_rac_account.mint == dao_config.rac_mint @ CustomError::InvalidRacMint,
+        constraint = proposer_rac_account.owner == proposer.key() @ CustomError::InvalidTokenOwner
+    )]
+    pub proposer_rac_account: Account<'info, TokenAccount>,
     #[account(mut)]
     pub proposer: Signer<'info>,
     pub system_program: Program<'info, System>,
@@ -713,7 +5642,7 @@ pub struct CreateConfigProposal<'info> {
         init,
         payer = proposer,
         space = Proposal::LEN,
-        seeds = [b"proposal", dao_config.proposal_count.to_le_bytes().as_ref()],
+        seeds = [b"proposal", (dao_config.proposal_count + 1).to_le_bytes().as_ref()],
         bump
     )]
     pub proposal: Account<'info, Proposal>,
@@ -721,12 +5650,17 @@ pub struct CreateConfigProposal<'info> {
         init,
         payer = proposer,
         space = ConfigProposal::LEN,
-        seeds = [b"config_proposal", dao_config.proposal_count.to_le_bytes().as_ref()],
+        seeds = [b"config_proposal", (dao_config.proposal_count + 1).to_le_bytes().as_ref()],
         bump
     )]
     pub config_proposal: Account<'info, ConfigProposal>,
-    #[account(mut)]
+    #[account(mut, seeds = [b"dao_config"], bump)]
     pub dao_config: Account<'info, DaoConfig>,
+    #[account(
+        constraint = proposer_rac_account.mint == dao_config.rac_mint @ CustomError::InvalidRacMint,
+        constraint = proposer_rac_account.owner == proposer.key() @ CustomError::InvalidTokenOwner
+    )]
+    pub proposer_rac_account: Account<'info, TokenAccount>,
     #[account(mut)]
     pub proposer: Signer<'info>,
     pub system_program: Program<'info, System>,
@@ -739,7 +5673,7 @@ pub struct CreateLoyaltyChangeProposal<'info> {
         init,
         payer = proposer,
         space = Proposal::LEN,
-        seeds = [b"proposal", dao_config.proposal_count.to_le_bytes().as_ref()],
+        seeds = [b"proposal", (dao_config.proposal_count + 1).to_le_bytes().as_ref()],
         bump
     )]
     pub proposal: Account<'info, Proposal>,
@@ -747,17 +5681,66 @@ pub struct CreateLoyaltyChangeProposal<'info> {
         init,
         payer = proposer,
         space = LoyaltyProposal::LEN,
-        seeds = [b"loyalty_proposal", dao_config.proposal_count.to_le_bytes().as_ref()],
+        seeds = [b"loyalty_proposal", (dao_config.proposal_count + 1).to_le_bytes().as_ref()],
         bump
     )]
     pub loyalty_proposal: Account<'info, LoyaltyProposal>,
+    #[account(mut, seeds = [b"dao_config"], bump)]
+    pub dao_config: Account<'info, DaoConfig>,
+    #[account(
+        constraint = proposer_rac_account.mint == dao_config.rac_mint @ CustomError::InvalidRacMint,
+        constraint = proposer_rac_account.owner == proposer.key() @ CustomError::InvalidTokenOwner
+    )]
+    pub proposer_rac_account: Account<'info, TokenAccount>,
     #[account(mut)]
+    pub proposer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// The account context for creating DAO config proposals.
+#[derive(Accounts)]
+pub struct CreateDaoConfigProposal<'info> {
+    #[account(
+        init,
+        payer = proposer,
+        space = Proposal::LEN,
+        seeds = [b"proposal", (dao_config.proposal_count + 1).to_le_bytes().as_ref()],
+        bump
+    )]
+    pub proposal: Account<'info, Proposal>,
+    #[account(
+        init,
+        payer = proposer,
+        space = DaoConfigProposal::LEN,
+        seeds = [b"dao_config_proposal", (dao_config.proposal_count + 1).to_le_bytes().as_ref()],
+        bump
+    )]
+    pub dao_config_proposal: Account<'info, DaoConfigProposal>,
+    #[account(mut, seeds = [b"dao_config"], bump)]
     pub dao_config: Account<'info, DaoConfig>,
+    #[account(
+        constraint = proposer_rac_account.mint == dao_config.rac_mint @ CustomError::InvalidRacMint,
+        constraint = proposer_rac_account.owner == proposer.key() @ CustomError::InvalidTokenOwner
+    )]
+    pub proposer_rac_account: Account<'info, TokenAccount>,
     #[account(mut)]
     pub proposer: Signer<'info>,
     pub system_program: Program<'info, System>,
 }
 
+/// The account context for executing DAO config proposals.
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct ExecuteDaoConfigProposal<'info> {
+    #[account(mut, seeds = [b"proposal", proposal_id.to_le_bytes().as_ref()], bump)]
+    pub proposal: Account<'info, Proposal>,
+    #[account(mut, seeds = [b"dao_config_proposal", proposal_id.to_le_bytes().as_ref()], bump)]
+    pub dao_config_proposal: Account<'info, DaoConfigProposal>,
+    #[account(mut, seeds = [b"dao_config"], bump)]
+    pub dao_config: Account<'info, DaoConfig>,
+    pub executor: Signer<'info>,
+}
+
 /// The account context for the `vote` instruction.
 #[derive(Accounts)]
 #[instruction(proposal_id: u64)]
@@ -773,12 +5756,160 @@ pub struct Vote<'info> {
         bump
     )]
     pub voter_record: Account<'info, VoterRecord>,
+    #[account(
+        mut,
+        seeds = [b"ve_lock", voter.key().as_ref()],
+        bump = ve_lock.bump
+    )]
+    pub ve_lock: Option<Account<'info, VeLock>>,
+    /// CHECK: The voter's delegation PDA. It is empty unless the voter has delegated.
+    #[account(seeds = [b"delegation", voter.key().as_ref()], bump)]
+    pub voter_delegation: UncheckedAccount<'info>,
+    // Card holder accounts, only read for `LoyaltyChange` proposals
+    pub loyalty_card: Option<Account<'info, LoyaltyCard>>,
+    pub card_tree: Option<Account<'info, CardTree>>,
+    /// CHECK: Concurrent Merkle tree registered in `card_tree`
+    #[account(owner = spl_account_compression::ID)]
+    pub merkle_tree: Option<UncheckedAccount<'info>>,
+    pub compression_program: Option<Program<'info, SplAccountCompression>>,
     #[account(mut)]
     pub voter: Signer<'info>,
-    pub rac_token_account: Account<'info, TokenAccount>,
     pub system_program: Program<'info, System>,
 }
 
+/// The account context for delegating voting power.
+#[derive(Accounts)]
+pub struct DelegateVotes<'info> {
+    #[account(
+        init,
+        payer = delegator,
+        space = Delegation::LEN,
+        seeds = [b"delegation", delegator.key().as_ref()],
+        bump
+    )]
+    pub delegation: Account<'info, Delegation>,
+    #[account(seeds = [b"ve_lock", delegator.key().as_ref()], bump = ve_lock.bump)]
+    pub ve_lock: Account<'info, VeLock>,
+    #[account(mut)]
+    pub delegator: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// The account context for revoking a delegation.
+#[derive(Accounts)]
+pub struct Undelegate<'info> {
+    #[account(
+        mut,
+        close = delegator,
+        seeds = [b"delegation", delegator.key().as_ref()],
+        bump = delegation.bump,
+        has_one = delegator @ CustomError::Unauthorized
+    )]
+    pub delegation: Account<'info, Delegation>,
+    #[account(seeds = [b"ve_lock", delegator.key().as_ref()], bump = ve_lock.bump)]
+    pub ve_lock: Account<'info, VeLock>,
+    #[account(mut)]
+    pub delegator: Signer<'info>,
+}
+
+/// The account context for creating a veRAC lock.
+#[derive(Accounts)]
+pub struct LockRac<'info> {
+    #[account(seeds = [b"dao_config"], bump)]
+    pub dao_config: Account<'info, DaoConfig>,
+    #[account(address = dao_config.rac_mint @ CustomError::InvalidRacMint)]
+    pub rac_mint: Account<'info, Mint>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = VeLock::LEN,
+        seeds = [b"ve_lock", owner.key().as_ref()],
+        bump
+    )]
+    pub ve_lock: Account<'info, VeLock>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        token::mint = rac_mint,
+        token::authority = ve_lock,
+        seeds = [b"ve_vault", owner.key().as_ref()],
+        bump
+    )]
+    pub ve_vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = owner_token_account.mint == dao_config.rac_mint @ CustomError::InvalidRacMint,
+        constraint = owner_token_account.owner == owner.key() @ CustomError::InvalidTokenOwner
+    )]
+    pub owner_token_account: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/// The account context for adding $RAC to an existing lock.
+#[derive(Accounts)]
+pub struct IncreaseLockAmount<'info> {
+    #[account(seeds = [b"dao_config"], bump)]
+    pub dao_config: Account<'info, DaoConfig>,
+    #[account(
+        mut,
+        seeds = [b"ve_lock", owner.key().as_ref()],
+        bump = ve_lock.bump,
+        has_one = owner @ CustomError::Unauthorized
+    )]
+    pub ve_lock: Account<'info, VeLock>,
+    #[account(mut, seeds = [b"ve_vault", owner.key().as_ref()], bump)]
+    pub ve_vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = owner_token_account.mint == dao_config.rac_mint @ CustomError::InvalidRacMint,
+        constraint = owner_token_account.owner == owner.key() @ CustomError::InvalidTokenOwner
+    )]
+    pub owner_token_account: Account<'info, TokenAccount>,
+    pub owner: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+/// The account context for extending a lock.
+#[derive(Accounts)]
+pub struct ExtendLock<'info> {
+    #[account(
+        mut,
+        seeds = [b"ve_lock", owner.key().as_ref()],
+        bump = ve_lock.bump,
+        has_one = owner @ CustomError::Unauthorized
+    )]
+    pub ve_lock: Account<'info, VeLock>,
+    pub owner: Signer<'info>,
+}
+
+/// The account context for withdrawing an expired lock.
+#[derive(Accounts)]
+pub struct WithdrawLock<'info> {
+    #[account(seeds = [b"dao_config"], bump)]
+    pub dao_config: Account<'info, DaoConfig>,
+    #[account(
+        mut,
+        seeds = [b"ve_lock", owner.key().as_ref()],
+        bump = ve_lock.bump,
+        has_one = owner @ CustomError::Unauthorized
+    )]
+    pub ve_lock: Account<'info, VeLock>,
+    #[account(mut, seeds = [b"ve_vault", owner.key().as_ref()], bump)]
+    pub ve_vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = owner_token_account.mint == dao_config.rac_mint @ CustomError::InvalidRacMint,
+        constraint = owner_token_account.owner == owner.key() @ CustomError::InvalidTokenOwner
+    )]
+    pub owner_token_account: Account<'info, TokenAccount>,
+    pub owner: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
 /// The account context for executing configuration proposals.
 #[derive(Accounts)]
 #[instruction(proposal_id: u64)]
@@ -789,27 +5920,132 @@ pub struct ExecuteConfigProposal<'info> {
     pub config_proposal: Account<'info, ConfigProposal>,
     #[account(mut, seeds = [b"rewards_config"], bump)]
     pub rewards_config: Account<'info, RewardsConfig>,
+    #[account(seeds = [b"dao_config"], bump)]
+    pub dao_config: Account<'info, DaoConfig>,
+    #[account(mut)]
+    pub executor: Signer<'info>,
+}
+
+/// The account context for activating a draft proposal.
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct ActivateProposal<'info> {
+    #[account(
+        mut,
+        seeds = [b"proposal", proposal_id.to_le_bytes().as_ref()],
+        bump,
+        constraint = proposal.proposer == proposer.key() @ CustomError::Unauthorized
+    )]
+    pub proposal: Account<'info, Proposal>,
+    #[account(seeds = [b"dao_config"], bump)]
+    pub dao_config: Account<'info, DaoConfig>,
+    pub proposer: Signer<'info>,
+}
+
+/// The account context for finalizing a proposal after its voting window.
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct FinalizeProposal<'info> {
+    #[account(mut, seeds = [b"proposal", proposal_id.to_le_bytes().as_ref()], bump)]
+    pub proposal: Account<'info, Proposal>,
+    #[account(seeds = [b"dao_config"], bump)]
+    pub dao_config: Account<'info, DaoConfig>,
+}
+
+/// The account context for cancelling a proposal.
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct CancelProposal<'info> {
+    #[account(
+        mut,
+        seeds = [b"proposal", proposal_id.to_le_bytes().as_ref()],
+        bump,
+        constraint = proposal.proposer == proposer.key() @ CustomError::Unauthorized
+    )]
+    pub proposal: Account<'info, Proposal>,
+    pub proposer: Signer<'info>,
+}
+
+/// The account context for attaching an instruction to a proposal.
+#[derive(Accounts)]
+#[instruction(proposal_id: u64, index: u16, program_id: Pubkey, accounts: Vec<ProposalAccountMeta>, data: Vec<u8>)]
+pub struct InsertProposalInstruction<'info> {
+    #[account(
+        mut,
+        seeds = [b"proposal", proposal_id.to_le_bytes().as_ref()],
+        bump,
+        constraint = proposal.proposer == proposer.key() @ CustomError::Unauthorized
+    )]
+    pub proposal: Account<'info, Proposal>,
+    #[account(
+        init,
+        payer = proposer,
+        space = ProposalInstruction::space(accounts.len(), data.len()),
+        seeds = [b"proposal_instruction", proposal_id.to_le_bytes().as_ref(), index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub proposal_instruction: Account<'info, ProposalInstruction>,
     #[account(mut)]
+    pub proposer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// The account context for executing a stored proposal instruction.
+#[derive(Accounts)]
+#[instruction(proposal_id: u64, index: u16)]
+pub struct ExecuteProposalInstruction<'info> {
+    #[account(mut, seeds = [b"proposal", proposal_id.to_le_bytes().as_ref()], bump)]
+    pub proposal: Account<'info, Proposal>,
+    #[account(
+        mut,
+        seeds = [b"proposal_instruction", proposal_id.to_le_bytes().as_ref(), index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub proposal_instruction: Account<'info, ProposalInstruction>,
+    // Read-only: instructions may CPI back into this program and update the config
+    #[account(seeds = [b"dao_config"], bump)]
+    pub dao_config: Account<'info, DaoConfig>,
+    /// CHECK: PDA that signs proposal instructions on behalf of the DAO. Holds no data.
+    #[account(seeds = [b"dao_authority"], bump)]
+    pub dao_authority: UncheckedAccount<'info>,
     pub executor: Signer<'info>,
 }
 
+/// The account context for the guardian vetoing a queued proposal.
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct VetoProposal<'info> {
+    #[account(mut, seeds = [b"proposal", proposal_id.to_le_bytes().as_ref()], bump)]
+    pub proposal: Account<'info, Proposal>,
+    #[account(seeds = [b"dao_config"], bump, has_one = guardian @ CustomError::Unauthorized)]
+    pub dao_config: Account<'info, DaoConfig>,
+    pub guardian: Signer<'info>,
+}
+
 
 // All Account Data Structures below...
 
 #[account]
+#[derive(InitSpace)]
 pub struct NftAccount {
+    pub version: u8,                       // Layout version, ACCOUNT_VERSION
     pub admin: Pubkey,
-    pub collection_name: String,           // Collection Name (dropdown list)
+    pub collection: Collection,            // Collection (dropdown list)
+    #[max_len(32)]
     pub nft_name: String,                  // NFT Name (e.g., Pearl White, Lava Orange)
+    #[max_len(32)]
     pub display_name: String,              // Display Name
+    #[max_len(10)]
     pub symbol: String,
+    #[max_len(200)]
     pub uri: String,
     pub custody_type: CustodyType,
     
     // Pricing & Minting
     pub buy_price_usdt: u64,               // Buy Price in USDT (in smallest unit)
-    pub rarity: String,                    // Common, Less Common, Rare, Very Rare
+    pub rarity: Rarity,                    // Common, Less Common, Rare, Very Rare
     pub mint_quantity: u64,                // Total number that can be minted
+    pub minted_count: u64,                 // Cards issued so far across all mint paths
     
     // Features & Capabilities
     pub is_upgradeable: bool,              // Upgrade (Yes/No)
@@ -817,7 +6053,7 @@ pub struct NftAccount {
     pub is_fractional_eligible: bool,      // Fractional (Yes/No)
     
     // Auto Staking
-    pub auto_staking_duration: String,     // Forever, 1 Year, 2 Years, 5 Years
+    pub auto_staking_duration: AutoStakingDuration, // Lockup for auto-staked rewards
     
     // Earning Ratios
     pub earn_on_spend_ratio: u64,          // Earn on Spend % (in basis points, e.g., 100 = 1.00%)
@@ -832,6 +6068,14 @@ pub struct NftAccount {
     pub custodial_income_rate: Option<u64>,
     pub last_distribution_timestamp: i64,
     
+    // Passive income accounting
+    pub acc_income_per_share: u128,        // Income per invested unit, scaled by INCOME_PRECISION
+    pub total_shares: u64,                 // Sum of all investment amounts for this NFT
+    
+    // Evolution metadata
+    #[max_len(200)]
+    pub evolved_uri: String,               // Metadata URI for evolved cards
+    
     // Metadata
     pub is_active: bool,
     pub created_at: i64,
@@ -840,75 +6084,274 @@ pub struct NftAccount {
 
 #[account]
 pub struct CustodialInvestment {
+    pub version: u8,
     pub investor: Pubkey,
     pub nft: Pubkey,
     pub amount: u64,
+    pub reward_debt: u128,
+    pub pending_income: u64,
+    pub last_claim_at: i64,
+    pub unlock_at: i64,                    // Escrowed funds withdrawable from this time
 }
 
 #[account]
 pub struct NonCustodialInvestment {
+    pub version: u8,
     pub investor: Pubkey,
     pub nft: Pubkey,
     pub amount: u64,
+    pub reward_debt: u128,
+    pub pending_income: u64,
+    pub last_claim_at: i64,
 }
 
 #[account]
 pub struct DaoConfig {
+    pub version: u8,
     pub rac_mint: Pubkey,
-    pub total_supply: u64,
+    pub total_supply: u64,               // Max supply cap in base units
+    pub minted_supply: u64,              // $RAC minted so far in base units
+    pub vesting_allocated: u64,          // $RAC committed to vesting schedules in base units
     pub proposal_count: u64,
     pub quorum_bps: u16,
     pub voting_period_slots: u64,
     pub min_proposal_threshold: u64,
     pub admin_authority: Pubkey,
+    pub execution_delay: i64,    // Seconds between approval and earliest execution
+    pub grace_period: i64,       // Seconds after the eta during which execution is allowed
+    pub guardian: Pubkey,        // May veto queued proposals during the delay
+    pub usdt_mint: Pubkey,       // Accepted USDT mint for treasury and purchases
+}
+
+#[account]
+pub struct FractionalNft {
+    pub version: u8,
+    pub parent_nft: Pubkey,
+    pub mint: Pubkey,
+    pub total_supply: u64,
+    pub supply_cap: u64,
+    pub sold: u64,                         // Shares transferred out of the fractional vault
+}
+
+#[account]
+pub struct Proposal {
+    pub version: u8,
+    pub proposal_id: u64,
+    pub proposer: Pubkey,
+    pub proposal_type: u8,
+    pub description: String,
+    pub votes_for: u64,
+    pub votes_against: u64,
+    pub votes_abstain: u64,
+    pub dao_org: u8,
+    pub status: u8,
+    pub created_at: i64,
+    pub end_slot: u64,
+    pub executed_at: Option<i64>,
+    pub instruction_count: u16,
+    pub instructions_executed: u16,
+    pub eta: i64,                // Earliest execution time, set when approved
+}
+
+/// An account reference inside a stored proposal instruction.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct ProposalAccountMeta {
+    pub pubkey: Pubkey,
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+/// An instruction that an approved Treasury or Governance proposal executes via CPI.
+#[account]
+pub struct ProposalInstruction {
+    pub version: u8,
+    pub proposal_id: u64,
+    pub index: u16,
+    pub program_id: Pubkey,
+    pub accounts: Vec<ProposalAccountMeta>,
+    pub data: Vec<u8>,
+    pub executed_at: Option<i64>,
+}
+
+/// A record to ensure a user only votes once per proposal.
+#[account]
+pub struct VoterRecord {
+    pub version: u8,
+    pub voter: Pubkey,
+    pub proposal_id: u64,
+    pub choice: VoteChoice,
+    pub weight: u64,
+    pub delegated_weight: u64,
+}
+
+/// Delegation of a holder's veRAC voting power to a representative.
+#[account]
+pub struct Delegation {
+    pub version: u8,
+    pub delegator: Pubkey,
+    pub delegate: Pubkey,
+    pub dao_org: Option<u8>, // None = all DAO orgs
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+impl Delegation {
+    pub fn applies_to(&self, dao_org: u8) -> bool {
+        self.dao_org.map_or(true, |org| org == dao_org)
+    }
+}
+
+/// A vote-escrowed $RAC position (veRAC). Voting power decays as `lock_end` approaches,
+/// and the tokens also stay locked until the last voted proposal's `end_slot`.
+#[account]
+pub struct VeLock {
+    pub version: u8,
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub lock_start: i64,
+    pub lock_end: i64,
+    pub locked_until_slot: u64,
+    pub bump: u8,
+}
+
+/// Per-asset bookkeeping for a PDA-owned treasury vault.
+#[account]
+pub struct TreasuryAsset {
+    pub version: u8,
+    pub kind: TreasuryAssetKind,
+    pub mint: Pubkey,
+    pub vault: Pubkey,
+    pub balance: u64,
+    pub total_deposited: u64,
+    pub total_withdrawn: u64,
+    pub bump: u8,
+}
+
+/// A cliff + linear $RAC release schedule for a team, ecosystem or rewards allocation.
+#[account]
+pub struct VestingSchedule {
+    pub version: u8,
+    pub beneficiary: Pubkey,
+    pub schedule_id: u64,
+    pub category: VestingCategory,
+    pub total_amount: u64,
+    pub claimed_amount: u64,
+    pub start_ts: i64,
+    pub cliff_ts: i64,
+    pub end_ts: i64,
+    pub revoked: bool,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+/// A Metaplex sized collection that loyalty cards are verified into.
+#[account]
+pub struct CardCollection {
+    pub version: u8,
+    pub collection: Collection,
+    pub mint: Pubkey,
+    pub size: u64,
+    pub bump: u8,
+}
+
+/// A minted loyalty card, keyed by its SPL mint.
+#[account]
+pub struct LoyaltyCard {
+    pub version: u8,
+    pub owner: Pubkey,
+    pub nft: Pubkey,                       // NftAccount the card was minted from
+    pub mint: Pubkey,
+    pub status: u8,                        // CardStatus
+    pub upgraded_to: Option<Pubkey>,       // Mint of the card this one was upgraded into
+    pub evolved: bool,
+    pub earn_ratio: u64,                   // Current earn-on-spend ratio in basis points
+    pub minted_at: i64,
+    pub bump: u8,
+}
+
+/// A program-owned concurrent Merkle tree holding compressed cards of one NFT.
+#[account]
+pub struct CardTree {
+    pub version: u8,
+    pub nft: Pubkey,
+    pub collection_mint: Pubkey,
+    pub merkle_tree: Pubkey,
+    pub capacity: u64,                     // 2^max_depth leaves
+    pub minted: u64,                       // Leaves minted; the next leaf's nonce
+    pub bump: u8,
+}
+
+/// Rewards auto-staked against an NFT tier, locked until `unlock_at`.
+#[account]
+pub struct RewardStake {
+    pub version: u8,
+    pub owner: Pubkey,
+    pub nft: Pubkey,
+    pub amount: u64,
+    pub unlock_at: i64,                    // i64::MAX for Forever
+    pub last_staked_at: i64,
+    pub bump: u8,
 }
 
+/// A merchant allowed to record customer spend.
 #[account]
-pub struct FractionalNft {
-    pub parent_nft: Pubkey,
-    pub mint: Pubkey,
-    pub total_supply: u64,
-    pub supply_cap: u64,
+pub struct Merchant {
+    pub version: u8,
+    pub authority: Pubkey,                 // Key that signs `record_spend`
+    pub name: String,
+    pub is_active: bool,
+    pub total_spend: u64,
+    pub total_rewarded: u64,
+    pub day: i64,                          // Unix timestamp / SECONDS_PER_DAY
+    pub rewarded_today: u64,
+    pub registered_at: i64,
+    pub bump: u8,
 }
 
+/// A recorded purchase. Its existence marks the receipt id as used.
 #[account]
-pub struct Proposal {
-    pub proposal_id: u64,
-    pub proposer: Pubkey,
-    pub proposal_type: u8,
-    pub description: String,
-    pub votes_for: u64,
-    pub votes_against: u64,
-    pub dao_org: u8,
-    pub status: u8,
-    pub created_at: i64,
-    pub end_slot: u64,
-    pub executed_at: Option<i64>,
+pub struct SpendReceipt {
+    pub version: u8,
+    pub merchant: Pubkey,
+    pub customer: Pubkey,
+    pub receipt_id: [u8; 32],
+    pub amount: u64,
+    pub reward: u64,
+    pub recorded_at: i64,
+    pub bump: u8,
 }
 
-/// A record to ensure a user only votes once per proposal.
+/// Rewards a customer has been credited on the current day, across all merchants.
 #[account]
-pub struct VoterRecord {
-    pub voter: Pubkey,
-    pub proposal_id: u64,
+pub struct SpendTracker {
+    pub version: u8,
+    pub customer: Pubkey,
+    pub day: i64,                          // Unix timestamp / SECONDS_PER_DAY
+    pub rewarded_today: u64,
+    pub bump: u8,
 }
 
 /// Rewards configuration account
 #[account]
 pub struct RewardsConfig {
+    pub version: u8,
     pub program_id: Pubkey,
     pub admin_authority: Pubkey,
     pub reward_token_mint: Pubkey,
     pub distribution_interval: u64,
     pub max_rewards_per_user: u64,
+    pub reward_vault: Pubkey,
+    pub outstanding_income: u64,           // Distributed but not yet claimed income
     pub is_active: bool,
     pub created_at: i64,
     pub updated_at: i64,
+    pub bump: u8,
 }
 
 /// Configuration proposal account
 #[account]
 pub struct ConfigProposal {
+    pub version: u8,
     pub proposal_id: u64,
     pub proposed_distribution_interval: u64,
     pub proposed_max_rewards_per_user: u64,
@@ -918,9 +6361,25 @@ pub struct ConfigProposal {
     pub implemented_at: Option<i64>,
 }
 
+/// A proposal to change the DAO's own governance parameters.
+#[account]
+pub struct DaoConfigProposal {
+    pub version: u8,
+    pub proposal_id: u64,
+    pub quorum_bps: u16,
+    pub voting_period_slots: u64,
+    pub min_proposal_threshold: u64,
+    pub execution_delay: i64,
+    pub grace_period: i64,
+    pub status: ConfigProposalStatus,
+    pub created_at: i64,
+    pub implemented_at: Option<i64>,
+}
+
 /// A proposal for loyalty application behavior changes.
 #[account]
 pub struct LoyaltyProposal {
+    pub version: u8,
     pub proposal_id: u64,
     pub change_type: u8,
     pub parameter_name: String,
@@ -935,36 +6394,300 @@ pub struct LoyaltyProposal {
 
 // Account length implementations
 impl NftAccount {
-    pub const LEN: usize = 8 + 32 + (4 + 50) + (4 + 10) + (4 + 200) + 1 + 8 + (1 + 8) + 8;
+    pub const LEN: usize = 8 + Self::INIT_SPACE;
 }
 impl CustodialInvestment {
-    pub const LEN: usize = 8 + 32 + 32 + 8;
+    pub const LEN: usize = 8 + 1 + 32 + 32 + 8 + 16 + 8 + 8 + 8;
 }
 impl NonCustodialInvestment {
-    pub const LEN: usize = 8 + 32 + 32 + 8;
+    pub const LEN: usize = 8 + 1 + 32 + 32 + 8 + 16 + 8 + 8;
 }
 impl DaoConfig {
-    pub const LEN: usize = 8 + 32 + 8 + 8 + 2 + 8 + 8 + 32;
+    pub const LEN: usize = 8 + 1 + 32 + 8 + 8 + 8 + 8 + 2 + 8 + 8 + 32 + 8 + 8 + 32 + 32;
 }
 impl FractionalNft {
-    pub const LEN: usize = 8 + 32 + 32 + 8 + 8;
+    pub const LEN: usize = 8 + 1 + 32 + 32 + 8 + 8 + 8;
 }
 impl Proposal {
-    pub const LEN: usize = 8 + 8 + 32 + 1 + (4 + 200) + 8 + 8 + 1 + 1 + 8 + 8 + (1 + 8);
+    pub const LEN: usize = 8 + 1 + 8 + 32 + 1 + (4 + 200) + 8 + 8 + 8 + 1 + 1 + 8 + 8 + (1 + 8) + 2 + 2 + 8;
+}
+impl ProposalInstruction {
+    pub fn space(accounts_len: usize, data_len: usize) -> usize {
+        8 + 1 + 8 + 2 + 32 + (4 + accounts_len * (32 + 1 + 1)) + (4 + data_len) + (1 + 8)
+    }
 }
 impl VoterRecord {
-    pub const LEN: usize = 8 + 32 + 8;
+    pub const LEN: usize = 8 + 1 + 32 + 8 + 1 + 8 + 8;
+}
+impl Delegation {
+    pub const LEN: usize = 8 + 1 + 32 + 32 + (1 + 1) + 8 + 1;
+}
+impl VeLock {
+    pub const LEN: usize = 8 + 1 + 32 + 8 + 8 + 8 + 8 + 1;
+}
+impl TreasuryAsset {
+    pub const LEN: usize = 8 + 1 + 1 + 32 + 32 + 8 + 8 + 8 + 1;
+}
+impl VestingSchedule {
+    pub const LEN: usize = 8 + 1 + 32 + 8 + 1 + 8 + 8 + 8 + 8 + 8 + 1 + 8 + 1;
+}
+impl CardCollection {
+    pub const LEN: usize = 8 + 1 + 1 + 32 + 8 + 1;
+}
+impl LoyaltyCard {
+    pub const LEN: usize = 8 + 1 + 32 + 32 + 32 + 1 + (1 + 32) + 1 + 8 + 8 + 1;
+}
+impl CardTree {
+    pub const LEN: usize = 8 + 1 + 32 + 32 + 32 + 8 + 8 + 1;
+}
+impl RewardStake {
+    pub const LEN: usize = 8 + 1 + 32 + 32 + 8 + 8 + 8 + 1;
+}
+impl Merchant {
+    pub const LEN: usize = 8 + 1 + 32 + (4 + MAX_MERCHANT_NAME_LEN) + 1 + 8 + 8 + 8 + 8 + 8 + 1;
+}
+impl SpendReceipt {
+    pub const LEN: usize = 8 + 1 + 32 + 32 + 32 + 8 + 8 + 8 + 1;
+}
+impl SpendTracker {
+    pub const LEN: usize = 8 + 1 + 32 + 8 + 8 + 1;
 }
 impl RewardsConfig {
-    pub const LEN: usize = 8 + 32 + 32 + 32 + 8 + 8 + 1 + 8 + 8;
+    pub const LEN: usize = 8 + 1 + 32 + 32 + 32 + 8 + 8 + 32 + 8 + 1 + 8 + 8 + 1;
 }
 impl ConfigProposal {
-    pub const LEN: usize = 8 + 8 + 8 + 8 + 1 + 8 + (1 + 8) + (1 + 8);
+    pub const LEN: usize = 8 + 1 + 8 + 8 + 8 + 1 + 8 + (1 + 8) + (1 + 8);
+}
+impl DaoConfigProposal {
+    pub const LEN: usize = 8 + 1 + 8 + 2 + 8 + 8 + 8 + 8 + 1 + 8 + (1 + 8);
 }
 impl LoyaltyProposal {
-    pub const LEN: usize = 8 + 8 + 1 + (4 + 100) + (4 + 500) + (4 + 500) + (4 + 1000) + 1 + 8 + (1 + 8) + (1 + 8);
+    pub const LEN: usize = 8 + 1 + 8 + 1 + (4 + 100) + (4 + 500) + (4 + 500) + (4 + 1000) + 1 + 8 + (1 + 8) + (1 + 8);
+}
+
+/// A compressed card leaf and the root it is proven against, from the DAS `getAssetProof` API.
+/// `proof_len` is the number of proof nodes passed first in the remaining accounts.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct CompressedCardProof {
+    pub root: [u8; 32],
+    pub data_hash: [u8; 32],
+    pub creator_hash: [u8; 32],
+    pub nonce: u64,
+    pub proof_len: u8,
+}
+
+/// One card tier definition for `initialize_catalog`; mirrors the `create_nft` arguments.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct CardTierSpec {
+    pub collection: Collection,
+    pub nft_name: String,
+    pub display_name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub custody_type: CustodyType,
+    pub buy_price_usdt: u64,
+    pub rarity: Rarity,
+    pub mint_quantity: u64,
+    pub is_upgradeable: bool,
+    pub is_evolvable: bool,
+    pub is_fractional_eligible: bool,
+    pub auto_staking_duration: AutoStakingDuration,
+    pub earn_on_spend_ratio: u64,
+    pub upgrade_bonus_ratio: u64,
+    pub evolution_min_investment: u64,
+    pub evolution_earnings_ratio: u64,
+    pub passive_income_rate: u64,
+    pub custodial_income_rate: Option<u64>,
+}
+
+// Client-side helper; not built into the on-chain program.
+#[cfg(not(target_os = "solana"))]
+impl CardTierSpec {
+    /// Splits a catalog into `initialize_catalog` batches, in order, that each fit one
+    /// transaction and hold at most `MAX_CATALOG_SPECS` tiers.
+    pub fn catalog_batches(specs: Vec<CardTierSpec>) -> Result<Vec<Vec<CardTierSpec>>> {
+        let mut batches: Vec<Vec<CardTierSpec>> = Vec::new();
+        let mut batch_size = CATALOG_TRANSACTION_OVERHEAD;
+
+        for spec in specs {
+            // The spec's arguments plus its NFT PDA key and account index.
+            let spec_size = spec
+                .try_to_vec()
+                .map_err(|_| error!(anchor_lang::error::ErrorCode::AccountDidNotSerialize))?
+                .len()
+                + 32
+                + 1;
+            match batches.last_mut() {
+                Some(batch) if batch.len() < MAX_CATALOG_SPECS && batch_size + spec_size <= MAX_TRANSACTION_SIZE => {
+                    batch_size += spec_size;
+                    batch.push(spec);
+                }
+                _ => {
+                    batch_size = CATALOG_TRANSACTION_OVERHEAD + spec_size;
+                    batches.push(vec![spec]);
+                }
+            }
+        }
+
+        Ok(batches)
+    }
+}
+
+impl CardTierSpec {
+    /// Builds the initial `NftAccount` for this tier. Custodial income rates are
+    /// dropped for non-custodial tiers.
+    pub fn into_nft_account(self, admin: Pubkey, now: i64) -> Result<NftAccount> {
+        validate_nft_fields(&self.nft_name, &self.display_name, &self.symbol, &self.uri)?;
+
+        let custodial_income_rate = match self.custody_type {
+            CustodyType::Custodial => self.custodial_income_rate,
+            CustodyType::NonCustodial => None,
+        };
+
+        Ok(NftAccount {
+            version: ACCOUNT_VERSION,
+            admin,
+            collection: self.collection,
+            nft_name: self.nft_name,
+            display_name: self.display_name,
+            symbol: self.symbol,
+            uri: self.uri,
+            custody_type: self.custody_type,
+            buy_price_usdt: self.buy_price_usdt,
+            rarity: self.rarity,
+            mint_quantity: self.mint_quantity,
+            minted_count: 0,
+            is_upgradeable: self.is_upgradeable,
+            is_evolvable: self.is_evolvable,
+            is_fractional_eligible: self.is_fractional_eligible,
+            auto_staking_duration: self.auto_staking_duration,
+            earn_on_spend_ratio: self.earn_on_spend_ratio,
+            upgrade_bonus_ratio: self.upgrade_bonus_ratio,
+            evolution_min_investment: self.evolution_min_investment,
+            evolution_earnings_ratio: self.evolution_earnings_ratio,
+            passive_income_rate: self.passive_income_rate,
+            custodial_income_rate,
+            last_distribution_timestamp: now,
+            acc_income_per_share: 0,
+            total_shares: 0,
+            evolved_uri: String::new(),
+            is_active: true,
+            created_at: now,
+            updated_at: now,
+        })
+    }
+}
+
+/// Return data for `get_evolution_eligibility`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct EvolutionEligibility {
+    pub card: Pubkey,
+    pub is_active: bool,
+    pub is_evolvable: bool,
+    pub already_evolved: bool,
+    pub invested_amount: u64,              // RAC base units
+    pub required_amount: u64,              // RAC base units
+    pub eligible: bool,
+}
+
+// Events
+#[event]
+pub struct ProposalQueued {
+    pub proposal_id: u64,
+    pub eta: i64,
+}
+
+#[event]
+pub struct ProposalVetoed {
+    pub proposal_id: u64,
+    pub guardian: Pubkey,
+    pub vetoed_at: i64,
+}
+
+#[event]
+pub struct ProposalExecuted {
+    pub proposal_id: u64,
+    pub executed_at: i64,
+}
+
+#[event]
+pub struct CardUpgraded {
+    pub owner: Pubkey,
+    pub old_mint: Pubkey,
+    pub new_mint: Pubkey,
+    pub from_nft: Pubkey,
+    pub to_nft: Pubkey,
+    pub price_paid: u64,
+    pub rac_bonus: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CardTransferred {
+    pub from: Pubkey,
+    pub to: Pubkey,
+    pub mint: Pubkey,
+    pub nft: Pubkey,
+    pub evolved: bool,
+    pub earn_ratio: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CardEvolved {
+    pub owner: Pubkey,
+    pub mint: Pubkey,
+    pub nft: Pubkey,
+    pub invested_amount: u64,
+    pub earn_ratio: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SpendRecorded {
+    pub merchant: Pubkey,
+    pub customer: Pubkey,
+    pub card: Pubkey,                      // Card mint, or asset id of a compressed card
+    pub receipt_id: [u8; 32],
+    pub amount: u64,
+    pub reward: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CompressedCardMinted {
+    pub nft: Pubkey,
+    pub merkle_tree: Pubkey,
+    pub asset_id: Pubkey,
+    pub owner: Pubkey,
+    pub nonce: u64,
+}
+
+#[event]
+pub struct CompressedCardVerified {
+    pub nft: Pubkey,
+    pub merkle_tree: Pubkey,
+    pub holder: Pubkey,
+    pub nonce: u64,
+}
+
+#[event]
+pub struct TreasuryDeposit {
+    pub mint: Pubkey,
+    pub from: Pubkey,
+    pub amount: u64,
+    pub balance: u64,
+    pub timestamp: i64,
 }
 
+#[event]
+pub struct TreasuryWithdrawal {
+    pub mint: Pubkey,
+    pub to: Pubkey,
+    pub amount: u64,
+    pub balance: u64,
+    pub timestamp: i64,
+}
 
 #[error_code]
 pub enum CustomError {
@@ -1004,4 +6727,954 @@ pub enum CustomError {
     VotingStillActive,
     #[msg("Quorum not met.")]
     QuorumNotMet,
+    #[msg("Proposal is not in draft status.")]
+    ProposalNotDraft,
+    #[msg("Proposal is not active.")]
+    ProposalNotActive,
+    #[msg("Proposal can no longer be cancelled.")]
+    ProposalCannotBeCancelled,
+    #[msg("Invalid vote type. Use 0 (against), 1 (for) or 2 (abstain).")]
+    InvalidVoteType,
+    #[msg("The voting period for this proposal has ended.")]
+    VotingPeriodEnded,
+    #[msg("Token account mint does not match the DAO's $RAC mint.")]
+    InvalidRacMint,
+    #[msg("Token account is not owned by the signer.")]
+    InvalidTokenOwner,
+    #[msg("Deposit amount must be greater than zero.")]
+    ZeroDepositAmount,
+    #[msg("Tokens are locked until the proposals you voted on have closed.")]
+    TokensLockedForVoting,
+    #[msg("Lock duration must be between one week and four years.")]
+    InvalidLockDuration,
+    #[msg("An active lock already exists for this owner.")]
+    LockAlreadyExists,
+    #[msg("No active lock found.")]
+    NoActiveLock,
+    #[msg("The lock has expired.")]
+    LockExpired,
+    #[msg("The lock has not expired yet.")]
+    LockNotExpired,
+    #[msg("The new lock end must be later than the current one.")]
+    LockEndNotExtended,
+    #[msg("You have delegated your voting power for this DAO org.")]
+    DelegatorCannotVote,
+    #[msg("You cannot delegate to yourself.")]
+    CannotDelegateToSelf,
+    #[msg("Invalid DAO org.")]
+    InvalidDaoOrg,
+    #[msg("Invalid delegation accounts provided.")]
+    InvalidDelegationAccounts,
+    #[msg("Instructions must be added and executed in order.")]
+    InvalidInstructionIndex,
+    #[msg("Proposal instruction exceeds the account or data limit.")]
+    InstructionTooLarge,
+    #[msg("Proposal instruction has already been executed.")]
+    InstructionAlreadyExecuted,
+    #[msg("Treasury and Governance proposals need at least one instruction before activation.")]
+    ProposalHasNoInstructions,
+    #[msg("Execution delay must be non-negative and grace period positive.")]
+    InvalidTimelockSettings,
+    #[msg("The proposal's timelock has not expired yet.")]
+    TimelockNotExpired,
+    #[msg("The proposal's timelock has already expired.")]
+    TimelockExpired,
+    #[msg("The proposal's execution window has passed.")]
+    ProposalExpired,
+    #[msg("Quorum must be between 0 and 10000 basis points.")]
+    InvalidQuorum,
+    #[msg("Voting period must be greater than zero.")]
+    InvalidVotingPeriod,
+    #[msg("Your $RAC balance is below the proposal threshold.")]
+    BelowProposalThreshold,
+    #[msg("Mint amount must be greater than zero.")]
+    ZeroMintAmount,
+    #[msg("Minting would exceed the $RAC max supply.")]
+    MaxSupplyExceeded,
+    #[msg("The DAO authority may only sign instructions for this program or program upgrades.")]
+    ProgramNotAllowed,
+    #[msg("Vesting amount must be greater than zero.")]
+    ZeroVestingAmount,
+    #[msg("Vesting schedule must satisfy start <= cliff <= end with start < end.")]
+    InvalidVestingSchedule,
+    #[msg("Nothing to claim yet.")]
+    NothingToClaim,
+    #[msg("Vesting schedule has already been revoked.")]
+    VestingAlreadyRevoked,
+    #[msg("This mint is not a supported treasury asset.")]
+    UnsupportedTreasuryAsset,
+    #[msg("Withdrawal amount must be greater than zero.")]
+    ZeroWithdrawalAmount,
+    #[msg("Insufficient treasury balance.")]
+    InsufficientTreasuryBalance,
+    #[msg("Rewards distribution is not active.")]
+    RewardsInactive,
+    #[msg("The distribution interval has not elapsed yet.")]
+    DistributionTooEarly,
+    #[msg("The reward vault does not hold enough tokens for this distribution.")]
+    InsufficientRewardFunds,
+    #[msg("The investment account for this NFT's custody type was not provided.")]
+    MissingInvestmentAccount,
+    #[msg("You can only claim once per distribution interval.")]
+    ClaimTooEarly,
+    #[msg("Token account mint does not match the reward token mint.")]
+    InvalidRewardMint,
+    #[msg("Fractional account does not belong to this NFT.")]
+    InvalidFractionalAccount,
+    #[msg("Token mint is not accepted for custodial investments.")]
+    InvalidInvestmentMint,
+    #[msg("Auto-staking duration must be Forever, 1 Year or N Years.")]
+    InvalidStakingDuration,
+    #[msg("This investment is still within its auto-staking lockup.")]
+    InvestmentLocked,
+    #[msg("Withdrawal exceeds the invested amount.")]
+    InsufficientInvestment,
+    #[msg("Fractional supply must be greater than zero.")]
+    InvalidFractionalSupply,
+    #[msg("The purchase cost exceeds the maximum the buyer accepted.")]
+    PriceExceedsMaximum,
+    #[msg("This NFT is not active.")]
+    NftNotActive,
+    #[msg("This card is sold out: all cards for this NFT have been minted.")]
+    MintQuantityExhausted,
+    #[msg("Collection must be Classic, Premium, Elite or Exclusive.")]
+    InvalidCollectionName,
+    #[msg("Name, symbol or URI exceeds the Metaplex metadata limits.")]
+    MetadataFieldTooLong,
+    #[msg("Compressed cards are only issued for free NFTs.")]
+    CompressedCardNotFree,
+    #[msg("Merkle tree depth must be between 1 and 30.")]
+    InvalidTreeDepth,
+    #[msg("The card tree is full.")]
+    CardTreeFull,
+    #[msg("The card tree does not belong to this NFT.")]
+    InvalidCardTree,
+    #[msg("The compressed card proof is invalid.")]
+    InvalidCompressedProof,
+    #[msg("Only holders of an active loyalty card can do this.")]
+    CardHolderRequired,
+    #[msg("This card is no longer active.")]
+    CardNotActive,
+    #[msg("Upgrades are only available for upgradeable custodial cards.")]
+    UpgradeNotAllowed,
+    #[msg("The target card must be a higher tier than the current card.")]
+    UpgradeNotHigherTier,
+    #[msg("Token account does not hold this card.")]
+    InvalidCardAccount,
+    #[msg("This NFT does not support evolution.")]
+    EvolutionNotAllowed,
+    #[msg("This card has already evolved.")]
+    CardAlreadyEvolved,
+    #[msg("No evolved metadata URI has been set for this NFT.")]
+    EvolvedUriNotSet,
+    #[msg("Invested and staked RAC is below the evolution minimum.")]
+    InsufficientEvolutionStake,
+    #[msg("Custodial income is auto-staked; use claim_staking_rewards.")]
+    AutoStakingRequired,
+    #[msg("Unstake amount exceeds the staked balance.")]
+    InsufficientStake,
+    #[msg("Staked rewards are still locked.")]
+    StakeLocked,
+    #[msg("Account is not an NFT account.")]
+    InvalidNftAccount,
+    #[msg("Account already uses the current layout.")]
+    AlreadyMigrated,
+    #[msg("Merchant name must be between 1 and 64 bytes.")]
+    InvalidMerchantName,
+    #[msg("This merchant is not active.")]
+    MerchantInactive,
+    #[msg("Spend amount must be greater than zero.")]
+    ZeroSpendAmount,
+    #[msg("The daily reward cap for this customer has been reached.")]
+    DailyRewardCapReached,
+    #[msg("The daily reward cap for this merchant has been reached.")]
+    MerchantRewardCapReached,
+    #[msg("The mint cap cannot be set below the number of cards already minted.")]
+    MintQuantityBelowMinted,
+    #[msg("Rarity must be Common, Less Common, Rare or Very Rare.")]
+    InvalidRarity,
+    #[msg("NFT name must be between 1 and 32 bytes.")]
+    InvalidNftName,
+    #[msg("Display name must be between 1 and 32 bytes.")]
+    InvalidDisplayName,
+    #[msg("Symbol must be at most 10 bytes.")]
+    SymbolTooLong,
+    #[msg("URI must be at most 200 bytes.")]
+    UriTooLong,
+    #[msg("A catalog batch must contain between 1 and 6 card tiers.")]
+    InvalidCatalogSize,
+    #[msg("Remaining accounts must be the NFT PDAs of the catalog specs, in order.")]
+    InvalidCatalogAccounts,
+    #[msg("Account data does not match any known layout.")]
+    UnknownAccountLayout,
+    #[msg("Custodial cards are soulbound and cannot be transferred.")]
+    CardNotTransferable,
+    #[msg("A card cannot be transferred to its current owner.")]
+    InvalidCardRecipient,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Account data as the old program allocated it: discriminator, body, zero padding to `len`.
+    fn fixture(discriminator: [u8; 8], body: Vec<u8>, len: usize) -> Vec<u8> {
+        let mut data = discriminator.to_vec();
+        data.extend(body);
+        assert!(data.len() <= len);
+        data.resize(len, 0);
+        data
+    }
+
+    fn borsh_string(value: &str) -> Vec<u8> {
+        let mut bytes = (value.len() as u32).to_le_bytes().to_vec();
+        bytes.extend_from_slice(value.as_bytes());
+        bytes
+    }
+
+    fn migration_args() -> migration::DaoConfigMigrationArgs {
+        migration::DaoConfigMigrationArgs {
+            quorum_bps: 2_000,
+            voting_period_slots: 216_000,
+            min_proposal_threshold: 1_000,
+            admin_authority: Pubkey::new_unique(),
+            execution_delay: 2 * SECONDS_PER_DAY,
+            grace_period: 7 * SECONDS_PER_DAY,
+            guardian: Pubkey::new_unique(),
+            usdt_mint: Pubkey::new_unique(),
+        }
+    }
+
+    #[test]
+    fn migrates_original_dao_config() {
+        let rac_mint = Pubkey::new_unique();
+        let mut body = rac_mint.to_bytes().to_vec();
+        body.extend(1_500_000_000u64.to_le_bytes());
+        body.extend(7u64.to_le_bytes());
+        let data = fixture(DaoConfig::DISCRIMINATOR, body, migration::DaoConfigRev1::LEN);
+        let args = migration_args();
+
+        let migrated = migration::upgrade_dao_config(&data, &args, RAC_INITIAL_SUPPLY).unwrap();
+
+        assert_eq!(migrated.version, ACCOUNT_VERSION);
+        assert_eq!(migrated.rac_mint, rac_mint);
+        assert_eq!(migrated.total_supply, RAC_MAX_SUPPLY);
+        assert_eq!(migrated.minted_supply, RAC_INITIAL_SUPPLY);
+        assert_eq!(migrated.proposal_count, 7);
+        assert_eq!(migrated.quorum_bps, args.quorum_bps);
+        assert_eq!(migrated.admin_authority, args.admin_authority);
+        assert_eq!(migrated.guardian, args.guardian);
+        assert_eq!(migrated.usdt_mint, args.usdt_mint);
+    }
+
+    #[test]
+    fn migrates_governance_dao_config_keeping_stored_parameters() {
+        let rac_mint = Pubkey::new_unique();
+        let admin = Pubkey::new_unique();
+        let mut body = rac_mint.to_bytes().to_vec();
+        body.extend(1_500_000_000u64.to_le_bytes());
+        body.extend(3u64.to_le_bytes());
+        body.extend(1_500u16.to_le_bytes());
+        body.extend(100_000u64.to_le_bytes());
+        body.extend(500u64.to_le_bytes());
+        body.extend(admin.to_bytes());
+        let data = fixture(DaoConfig::DISCRIMINATOR, body, migration::DaoConfigRev2::LEN);
+        let args = migration_args();
+
+        let migrated = migration::upgrade_dao_config(&data, &args, RAC_INITIAL_SUPPLY).unwrap();
+
+        assert_eq!(migrated.proposal_count, 3);
+        assert_eq!(migrated.quorum_bps, 1_500);
+        assert_eq!(migrated.voting_period_slots, 100_000);
+        assert_eq!(migrated.min_proposal_threshold, 500);
+        assert_eq!(migrated.admin_authority, admin);
+        assert_eq!(migrated.vesting_allocated, 0);
+        assert_eq!(migrated.execution_delay, args.execution_delay);
+        assert_eq!(migrated.grace_period, args.grace_period);
+    }
+
+    #[test]
+    fn migrates_unversioned_dao_config() {
+        let current = migration::DaoConfigRev2 {
+            rac_mint: Pubkey::new_unique(),
+            total_supply: 0,
+            proposal_count: 12,
+            quorum_bps: 1_000,
+            voting_period_slots: 50_000,
+            min_proposal_threshold: 10,
+            admin_authority: Pubkey::new_unique(),
+        }
+        .into_current(&migration_args(), RAC_INITIAL_SUPPLY + 5);
+        let mut data = Vec::new();
+        current.try_serialize(&mut data).unwrap();
+        data.remove(8);
+        let data = fixture(data[..8].try_into().unwrap(), data[8..].to_vec(), migration::DAO_CONFIG_REV3_LEN);
+
+        let migrated = migration::upgrade_dao_config(&data, &migration_args(), 0).unwrap();
+
+        assert_eq!(migrated.version, ACCOUNT_VERSION);
+        assert_eq!(migrated.rac_mint, current.rac_mint);
+        assert_eq!(migrated.minted_supply, RAC_INITIAL_SUPPLY + 5);
+        assert_eq!(migrated.proposal_count, 12);
+        assert_eq!(migrated.guardian, current.guardian);
+        assert_eq!(migrated.usdt_mint, current.usdt_mint);
+    }
+
+    #[test]
+    fn rejects_migrated_dao_config() {
+        let current = migration::DaoConfigRev1 {
+            rac_mint: Pubkey::new_unique(),
+            total_supply: 0,
+            proposal_count: 0,
+        }
+        .into_current(&migration_args(), 0);
+        let mut data = Vec::new();
+        current.try_serialize(&mut data).unwrap();
+        data.resize(DaoConfig::LEN, 0);
+
+        expect_error(
+            migration::upgrade_dao_config(&data, &migration_args(), 0),
+            CustomError::AlreadyMigrated,
+        );
+    }
+
+    #[test]
+    fn migrates_original_proposal_as_cancelled() {
+        let proposer = Pubkey::new_unique();
+        let mut body = 4u64.to_le_bytes().to_vec();
+        body.extend(proposer.to_bytes());
+        body.push(ProposalType::Treasury as u8);
+        body.extend(borsh_string("Fund the Q3 campaign"));
+        body.extend(900u64.to_le_bytes());
+        body.extend(100u64.to_le_bytes());
+        let data = fixture(Proposal::DISCRIMINATOR, body, migration::ProposalRev1::LEN);
+
+        let migrated = migration::upgrade_proposal(&data).unwrap();
+
+        assert_eq!(migrated.version, ACCOUNT_VERSION);
+        assert_eq!(migrated.proposal_id, 4);
+        assert_eq!(migrated.proposer, proposer);
+        assert_eq!(migrated.proposal_type, ProposalType::Treasury as u8);
+        assert_eq!(migrated.description, "Fund the Q3 campaign");
+        assert_eq!(migrated.votes_for, 900);
+        assert_eq!(migrated.votes_against, 100);
+        assert_eq!(migrated.status, ProposalStatus::Cancelled as u8);
+        assert_eq!(migrated.instruction_count, 0);
+    }
+
+    #[test]
+    fn migrates_lifecycle_proposal_keeping_status() {
+        let mut body = 9u64.to_le_bytes().to_vec();
+        body.extend(Pubkey::new_unique().to_bytes());
+        body.push(ProposalType::General as u8);
+        body.extend(borsh_string("Add a merchant category"));
+        body.extend(40u64.to_le_bytes());
+        body.extend(60u64.to_le_bytes());
+        body.push(2);
+        body.push(ProposalStatus::Rejected as u8);
+        body.extend(1_700_000_000i64.to_le_bytes());
+        body.extend(250_000u64.to_le_bytes());
+        body.push(0);
+        let data = fixture(Proposal::DISCRIMINATOR, body, migration::ProposalRev2::LEN);
+
+        let migrated = migration::upgrade_proposal(&data).unwrap();
+
+        assert_eq!(migrated.proposal_id, 9);
+        assert_eq!(migrated.dao_org, 2);
+        assert_eq!(migrated.status, ProposalStatus::Rejected as u8);
+        assert_eq!(migrated.created_at, 1_700_000_000);
+        assert_eq!(migrated.end_slot, 250_000);
+        assert_eq!(migrated.executed_at, None);
+        assert_eq!(migrated.votes_abstain, 0);
+        assert_eq!(migrated.eta, 0);
+    }
+
+    #[test]
+    fn migrates_original_nft_account() {
+        let admin = Pubkey::new_unique();
+        let mut body = admin.to_bytes().to_vec();
+        body.extend(borsh_string("Premium"));
+        body.extend(borsh_string("Lava Orange"));
+        body.extend(borsh_string("Lava Orange"));
+        body.extend(borsh_string("LAVA"));
+        body.extend(borsh_string("https://example.com/lava.json"));
+        body.push(CustodyType::Custodial as u8);
+        body.extend(100_000_000u64.to_le_bytes());
+        body.extend(borsh_string("Less Common"));
+        body.extend(3_000u64.to_le_bytes());
+        body.extend([1, 1, 0]);
+        body.extend(borsh_string("1 Year"));
+        body.extend(110u64.to_le_bytes());
+        body.extend(10u64.to_le_bytes());
+        body.extend(500u64.to_le_bytes());
+        body.extend(50u64.to_le_bytes());
+        body.extend(300u64.to_le_bytes());
+        body.push(1);
+        body.extend(400u64.to_le_bytes());
+        body.extend(1_690_000_000i64.to_le_bytes());
+        body.push(1);
+        body.extend(1_680_000_000i64.to_le_bytes());
+        body.extend(1_685_000_000i64.to_le_bytes());
+        let data = fixture(NftAccount::DISCRIMINATOR, body, migration::NftAccountRev1::LEN);
+
+        let migrated = migration::upgrade_nft_account(&data).unwrap();
+
+        assert_eq!(migrated.version, ACCOUNT_VERSION);
+        assert_eq!(migrated.admin, admin);
+        assert!(migrated.collection == Collection::Premium);
+        assert_eq!(migrated.nft_name, "Lava Orange");
+        assert_eq!(migrated.symbol, "LAVA");
+        assert!(migrated.custody_type == CustodyType::Custodial);
+        assert!(migrated.rarity == Rarity::LessCommon);
+        assert!(migrated.auto_staking_duration == AutoStakingDuration::OneYear);
+        assert_eq!(migrated.mint_quantity, 3_000);
+        assert_eq!(migrated.minted_count, 0);
+        assert_eq!(migrated.earn_on_spend_ratio, 110);
+        assert_eq!(migrated.custodial_income_rate, Some(400));
+        assert_eq!(migrated.total_shares, 0);
+        assert!(migrated.evolved_uri.is_empty());
+        assert!(migrated.is_active);
+        assert_eq!(migrated.updated_at, 1_685_000_000);
+    }
+
+    #[test]
+    fn rejects_unknown_layouts() {
+        let data = fixture(Proposal::DISCRIMINATOR, Vec::new(), 100);
+        expect_error(migration::upgrade_proposal(&data), CustomError::UnknownAccountLayout);
+
+        let data = fixture(VeLock::DISCRIMINATOR, Vec::new(), VeLock::LEN);
+        expect_error(migration::upgrade_proposal(&data), CustomError::UnknownAccountLayout);
+    }
+
+    #[test]
+    fn finds_versioned_length_by_discriminator() {
+        let data = fixture(VeLock::DISCRIMINATOR, Vec::new(), VeLock::LEN - 1);
+        assert_eq!(migration::versioned_account_len(&data).unwrap(), VeLock::LEN);
+
+        let data = fixture(Proposal::DISCRIMINATOR, Vec::new(), Proposal::LEN - 1);
+        expect_error(migration::versioned_account_len(&data), CustomError::UnknownAccountLayout);
+    }
+
+    fn expect_error<T>(result: Result<T>, expected: CustomError) {
+        let expected: Error = expected.into();
+        assert_eq!(result.err().unwrap(), expected);
+    }
+
+    #[test]
+    fn ve_voting_power_is_zero_at_and_after_expiry() {
+        assert_eq!(ve_voting_power(1_000, 100, 100), 0);
+        assert_eq!(ve_voting_power(1_000, 100, 101), 0);
+        assert_eq!(ve_voting_power(1_000, 100, i64::MAX), 0);
+    }
+
+    #[test]
+    fn ve_voting_power_clamps_to_max_lock_duration() {
+        assert_eq!(ve_voting_power(1_000, MAX_LOCK_DURATION, 0), 1_000);
+        assert_eq!(ve_voting_power(1_000, MAX_LOCK_DURATION * 2, 0), 1_000);
+        assert_eq!(ve_voting_power(1_000, i64::MAX, 0), 1_000);
+    }
+
+    #[test]
+    fn ve_voting_power_decays_linearly() {
+        let amount = 4_000_000;
+        let now = 1_700_000_000;
+        let lock_end = now + MAX_LOCK_DURATION;
+
+        assert_eq!(ve_voting_power(amount, lock_end, now), amount);
+        assert_eq!(ve_voting_power(amount, lock_end, now + MAX_LOCK_DURATION / 4), amount / 4 * 3);
+        assert_eq!(ve_voting_power(amount, lock_end, now + MAX_LOCK_DURATION / 2), amount / 2);
+        assert_eq!(
+            ve_voting_power(amount, lock_end, lock_end - MIN_LOCK_DURATION),
+            amount * MIN_LOCK_DURATION as u64 / MAX_LOCK_DURATION as u64
+        );
+        assert_eq!(ve_voting_power(amount, lock_end, lock_end - 1), 0);
+    }
+
+    #[test]
+    fn ve_voting_power_does_not_overflow_at_u64_max() {
+        assert_eq!(ve_voting_power(u64::MAX, MAX_LOCK_DURATION, 0), u64::MAX);
+        assert_eq!(ve_voting_power(u64::MAX, MAX_LOCK_DURATION / 2, 0), u64::MAX / 2);
+        assert!(ve_voting_power(u64::MAX, 1, 0) < u64::MAX);
+    }
+
+    #[test]
+    fn mint_rac_stops_at_max_supply() {
+        assert_eq!(checked_supply_add(RAC_INITIAL_SUPPLY, 1, RAC_MAX_SUPPLY).unwrap(), RAC_INITIAL_SUPPLY + 1);
+        assert_eq!(
+            checked_supply_add(RAC_INITIAL_SUPPLY, RAC_MAX_SUPPLY - RAC_INITIAL_SUPPLY, RAC_MAX_SUPPLY).unwrap(),
+            RAC_MAX_SUPPLY
+        );
+        expect_error(checked_supply_add(RAC_MAX_SUPPLY, 1, RAC_MAX_SUPPLY), CustomError::MaxSupplyExceeded);
+        expect_error(
+            checked_supply_add(RAC_INITIAL_SUPPLY, RAC_MAX_SUPPLY - RAC_INITIAL_SUPPLY + 1, RAC_MAX_SUPPLY),
+            CustomError::MaxSupplyExceeded,
+        );
+        expect_error(checked_supply_add(u64::MAX, 1, RAC_MAX_SUPPLY), CustomError::MaxSupplyExceeded);
+    }
+
+    #[test]
+    fn dao_authority_only_signs_for_this_program_and_upgrades() {
+        let (dao_authority, _) = Pubkey::find_program_address(&[b"dao_authority"], &crate::ID);
+        let signed = vec![ProposalAccountMeta { pubkey: dao_authority, is_signer: true, is_writable: false }];
+        let unsigned = vec![ProposalAccountMeta { pubkey: dao_authority, is_signer: false, is_writable: false }];
+        let mint_rac = crate::instruction::MintRac::DISCRIMINATOR.to_vec();
+        let treasury = ProposalType::Treasury as u8;
+        let governance = ProposalType::Governance as u8;
+
+        assert!(check_proposal_instruction(treasury, &crate::ID, &signed, &mint_rac).is_ok());
+        expect_error(
+            check_proposal_instruction(governance, &crate::ID, &signed, &mint_rac),
+            CustomError::InvalidProposalType,
+        );
+        expect_error(check_proposal_instruction(treasury, &spl_token::ID, &signed, &[7]), CustomError::ProgramNotAllowed);
+        expect_error(check_proposal_instruction(governance, &spl_token::ID, &signed, &[6]), CustomError::ProgramNotAllowed);
+        assert!(check_proposal_instruction(governance, &spl_token::ID, &unsigned, &[7]).is_ok());
+        assert!(check_proposal_instruction(governance, &bpf_loader_upgradeable::ID, &signed, &[3]).is_ok());
+        expect_error(
+            check_proposal_instruction(treasury, &bpf_loader_upgradeable::ID, &signed, &[3]),
+            CustomError::InvalidProposalType,
+        );
+    }
+
+    #[test]
+    fn quorum_is_reachable_with_the_initial_supply() {
+        let quorum = RAC_INITIAL_SUPPLY / 5;
+        assert!(is_quorum_met(quorum, RAC_INITIAL_SUPPLY, 2_000));
+        assert!(!is_quorum_met(quorum - 1, RAC_INITIAL_SUPPLY, 2_000));
+        // The whole initial supply locked for the maximum duration still counts in full.
+        assert!(is_quorum_met(ve_voting_power(RAC_INITIAL_SUPPLY, MAX_LOCK_DURATION, 0), RAC_INITIAL_SUPPLY, 10_000));
+    }
+
+    #[test]
+    fn vesting_allocations_stop_at_max_supply() {
+        let allocated = checked_supply_add(0, RAC_MAX_SUPPLY / 2, RAC_MAX_SUPPLY).unwrap();
+        let allocated = checked_supply_add(allocated, RAC_MAX_SUPPLY / 2, RAC_MAX_SUPPLY).unwrap();
+        assert_eq!(allocated, RAC_MAX_SUPPLY);
+        expect_error(checked_supply_add(allocated, 1, RAC_MAX_SUPPLY), CustomError::MaxSupplyExceeded);
+    }
+
+    #[test]
+    fn income_scales_from_usdt_to_reward_decimals() {
+        // 1.5 USDT (6 decimals) is 1.5 RAC (9 decimals)
+        assert_eq!(usdt_to_reward_units(1_500_000, 6, RAC_DECIMALS).unwrap(), 1_500_000_000);
+        assert_eq!(usdt_to_reward_units(1_500_000, 6, 6).unwrap(), 1_500_000);
+        assert_eq!(usdt_to_reward_units(1_500_000, 6, 2).unwrap(), 150);
+        expect_error(usdt_to_reward_units(u64::MAX as u128, 6, RAC_DECIMALS), CustomError::InsufficientRewardFunds);
+        assert_eq!(upgrade_bonus_amount(100_000_000, 1_000, 6).unwrap(), 10 * 10u64.pow(RAC_DECIMALS as u32));
+    }
+
+    // Checks `verify_compressed_card` against the real SPL account compression program.
+    // Needs `anchor build` and the compression and noop fixtures from the README in `tests/fixtures`;
+    // run with `cargo test -- --ignored`.
+    mod compressed_card_fixture {
+        use super::*;
+        use anchor_lang::solana_program::keccak::hashv;
+        use anchor_lang::solana_program::system_instruction;
+        use anchor_lang::{InstructionData, ToAccountMetas};
+        use solana_program_test::{BanksClientError, ProgramTest, ProgramTestContext};
+        use solana_sdk::account::Account as SolanaAccount;
+        use solana_sdk::signature::{Keypair, Signer as _};
+        use solana_sdk::transaction::Transaction;
+
+        const MAX_DEPTH: usize = 3;
+        const MAX_BUFFER_SIZE: usize = 8;
+
+        // Header plus `ConcurrentMerkleTree<MAX_DEPTH, MAX_BUFFER_SIZE>`: three u64 counters,
+        // the change log ring buffer and the rightmost path, with no canopy.
+        fn merkle_tree_size() -> usize {
+            let change_log = 32 + 32 * MAX_DEPTH + 8;
+            let path = 32 * MAX_DEPTH + 32 + 8;
+            spl_account_compression::state::CONCURRENT_MERKLE_TREE_HEADER_SIZE_V1 + 24 + change_log * MAX_BUFFER_SIZE + path
+        }
+
+        fn empty_node(level: usize) -> [u8; 32] {
+            let mut node = [0u8; 32];
+            for _ in 0..level {
+                node = hashv(&[&node, &node]).to_bytes();
+            }
+            node
+        }
+
+        fn card_leaf(merkle_tree: &Pubkey, holder: Pubkey, card_proof: &CompressedCardProof) -> [u8; 32] {
+            let custody = card_custody_address(&holder);
+            LeafSchema::V1 {
+                id: mpl_bubblegum::utils::get_asset_id(merkle_tree, card_proof.nonce),
+                owner: custody,
+                delegate: custody,
+                nonce: card_proof.nonce,
+                data_hash: card_proof.data_hash,
+                creator_hash: card_proof.creator_hash,
+            }
+            .hash()
+        }
+
+        async fn send(
+            context: &mut ProgramTestContext,
+            instructions: &[Instruction],
+            signers: &[&Keypair],
+        ) -> std::result::Result<(), BanksClientError> {
+            let blockhash = context.banks_client.get_latest_blockhash().await.unwrap();
+            let mut all_signers = vec![&context.payer];
+            all_signers.extend_from_slice(signers);
+            let transaction =
+                Transaction::new_signed_with_payer(instructions, Some(&context.payer.pubkey()), &all_signers, blockhash);
+            context.banks_client.process_transaction(transaction).await
+        }
+
+        fn verify_instruction(
+            card_tree: Pubkey,
+            merkle_tree: Pubkey,
+            holder: Pubkey,
+            card_proof: CompressedCardProof,
+            proof: &[[u8; 32]],
+        ) -> Instruction {
+            let mut accounts = crate::accounts::VerifyCompressedCard {
+                card_tree,
+                merkle_tree,
+                holder,
+                compression_program: spl_account_compression::ID,
+            }
+            .to_account_metas(None);
+            accounts.extend(proof.iter().map(|node| AccountMeta::new_readonly(Pubkey::new_from_array(*node), false)));
+            Instruction {
+                program_id: crate::ID,
+                accounts,
+                data: crate::instruction::VerifyCompressedCard { card_proof }.data(),
+            }
+        }
+
+        #[tokio::test]
+        #[ignore = "needs anchor build and the program fixtures from the README"]
+        async fn verifies_compressed_card_against_tree_fixture() {
+            let merkle_tree = Keypair::new();
+            let holder = Keypair::new();
+            let (card_tree, card_tree_bump) =
+                Pubkey::find_program_address(&[b"card_tree", merkle_tree.pubkey().as_ref()], &crate::ID);
+
+            let mut program_test = ProgramTest::new("solana_dao_nft_contract", crate::ID, None);
+            program_test.add_program("spl_account_compression", spl_account_compression::ID, None);
+            program_test.add_program("spl_noop", Noop::id(), None);
+
+            let mut data = Vec::new();
+            CardTree {
+                version: ACCOUNT_VERSION,
+                nft: Pubkey::new_unique(),
+                collection_mint: Pubkey::new_unique(),
+                merkle_tree: merkle_tree.pubkey(),
+                capacity: 1 << MAX_DEPTH,
+                minted: 1,
+                bump: card_tree_bump,
+            }
+            .try_serialize(&mut data)
+            .unwrap();
+            data.resize(CardTree::LEN, 0);
+            program_test.add_account(
+                card_tree,
+                SolanaAccount { lamports: 1_000_000_000, data, owner: crate::ID, executable: false, rent_epoch: 0 },
+            );
+
+            let mut context = program_test.start_with_context().await;
+            let payer = context.payer.pubkey();
+
+            let card_proof = CompressedCardProof {
+                root: [0; 32],
+                data_hash: [1; 32],
+                creator_hash: [2; 32],
+                nonce: 0,
+                proof_len: MAX_DEPTH as u8,
+            };
+            let leaf = card_leaf(&merkle_tree.pubkey(), holder.pubkey(), &card_proof);
+
+            // Leaf 0 of an otherwise empty tree: every sibling is an empty subtree
+            let proof: Vec<[u8; 32]> = (0..MAX_DEPTH).map(empty_node).collect();
+            let root = proof.iter().fold(leaf, |node, sibling| hashv(&[&node, sibling]).to_bytes());
+            let card_proof = CompressedCardProof { root, ..card_proof };
+
+            let size = merkle_tree_size();
+            let lamports = context.banks_client.get_rent().await.unwrap().minimum_balance(size);
+            let create_tree = system_instruction::create_account(
+                &payer,
+                &merkle_tree.pubkey(),
+                lamports,
+                size as u64,
+                &spl_account_compression::ID,
+            );
+            let init_tree = Instruction {
+                program_id: spl_account_compression::ID,
+                accounts: spl_account_compression::accounts::Initialize {
+                    merkle_tree: merkle_tree.pubkey(),
+                    authority: payer,
+                    noop: Noop::id(),
+                }
+                .to_account_metas(None),
+                data: spl_account_compression::instruction::InitEmptyMerkleTree {
+                    max_depth: MAX_DEPTH as u32,
+                    max_buffer_size: MAX_BUFFER_SIZE as u32,
+                }
+                .data(),
+            };
+            let append_leaf = Instruction {
+                program_id: spl_account_compression::ID,
+                accounts: spl_account_compression::accounts::Modify {
+                    merkle_tree: merkle_tree.pubkey(),
+                    authority: payer,
+                    noop: Noop::id(),
+                }
+                .to_account_metas(None),
+                data: spl_account_compression::instruction::Append { leaf }.data(),
+            };
+            send(&mut context, &[create_tree, init_tree, append_leaf], &[&merkle_tree]).await.unwrap();
+
+            let verify = verify_instruction(card_tree, merkle_tree.pubkey(), holder.pubkey(), card_proof.clone(), &proof);
+            send(&mut context, &[verify], &[&holder]).await.unwrap();
+
+            let impostor = Keypair::new();
+            let verify = verify_instruction(card_tree, merkle_tree.pubkey(), impostor.pubkey(), card_proof, &proof);
+            assert!(send(&mut context, &[verify], &[&impostor]).await.is_err());
+        }
+    }
+
+    #[test]
+    fn evolution_minimum_is_converted_to_rac() {
+        let mut spec = catalog_spec("Gold", 0);
+        spec.evolution_min_investment = 100_000_000; // 100 USDT
+        let nft = spec.into_nft_account(Pubkey::new_unique(), 0).unwrap();
+        let card = LoyaltyCard {
+            version: ACCOUNT_VERSION,
+            owner: Pubkey::new_unique(),
+            nft: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            status: CardStatus::Active as u8,
+            upgraded_to: None,
+            evolved: false,
+            earn_ratio: 100,
+            minted_at: 0,
+            bump: 0,
+        };
+        let rac = 10u64.pow(RAC_DECIMALS as u32);
+
+        let eligibility = evolution_eligibility(&card, &nft, 100 * rac, 6).unwrap();
+        assert_eq!(eligibility.required_amount, 100 * rac);
+        assert!(eligibility.eligible);
+
+        assert!(!evolution_eligibility(&card, &nft, 100 * rac - 1, 6).unwrap().eligible);
+        // The raw USDT figure is only a tenth of a RAC.
+        assert!(!evolution_eligibility(&card, &nft, 100_000_000, 6).unwrap().eligible);
+    }
+
+    #[test]
+    fn daily_reward_window_resets_each_day() {
+        let (mut day, mut rewarded_today) = (0i64, 0u64);
+        assert_eq!(daily_reward_remaining(&mut day, &mut rewarded_today, 100, 5), 100);
+        rewarded_today += 100;
+        assert_eq!(daily_reward_remaining(&mut day, &mut rewarded_today, 100, 5), 0);
+        assert_eq!(daily_reward_remaining(&mut day, &mut rewarded_today, 100, 6), 100);
+        assert_eq!((day, rewarded_today), (6, 0));
+    }
+
+    #[test]
+    fn spend_rewards_scale_usdt_to_reward_units() {
+        // 25 USDT at 1% earns a quarter of a RAC.
+        assert_eq!(spend_reward_amount(25_000_000, 100, 6, RAC_DECIMALS).unwrap(), 250_000_000);
+        assert_eq!(spend_reward_amount(25_000_000, 100, 6, 6).unwrap(), 250_000);
+        assert_eq!(spend_reward_amount(99, 100, 6, 6).unwrap(), 0);
+    }
+
+    #[test]
+    fn merchant_cap_follows_the_customer_cap() {
+        assert_eq!(merchant_daily_reward_cap(1_000), 1_000 * MERCHANT_DAILY_CAP_MULTIPLIER);
+        assert_eq!(merchant_daily_reward_cap(0), 0);
+        assert_eq!(merchant_daily_reward_cap(u64::MAX), u64::MAX);
+    }
+
+    fn catalog_spec(name: &str, uri_len: usize) -> CardTierSpec {
+        CardTierSpec {
+            collection: Collection::Classic,
+            nft_name: name.to_string(),
+            display_name: "N".repeat(MAX_METADATA_NAME_LEN),
+            symbol: "S".repeat(MAX_METADATA_SYMBOL_LEN),
+            uri: "u".repeat(uri_len),
+            custody_type: CustodyType::Custodial,
+            buy_price_usdt: 100_000_000,
+            rarity: Rarity::Common,
+            mint_quantity: 1_000,
+            is_upgradeable: true,
+            is_evolvable: true,
+            is_fractional_eligible: false,
+            auto_staking_duration: AutoStakingDuration::OneYear,
+            earn_on_spend_ratio: 100,
+            upgrade_bonus_ratio: 100,
+            evolution_min_investment: 0,
+            evolution_earnings_ratio: 200,
+            passive_income_rate: 500,
+            custodial_income_rate: Some(500),
+        }
+    }
+
+    // Builds and signs the `initialize_catalog` transaction a client would send for `batch`.
+    fn catalog_transaction(batch: &[CardTierSpec]) -> solana_sdk::transaction::Transaction {
+        use anchor_lang::{InstructionData, ToAccountMetas};
+        use solana_sdk::signature::{Keypair, Signer as _};
+
+        let admin = Keypair::new();
+        let mut accounts = crate::accounts::InitializeCatalog {
+            admin: admin.pubkey(),
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None);
+        accounts.extend(batch.iter().map(|spec| {
+            let (nft, _) = Pubkey::find_program_address(
+                &[b"nft", &[spec.custody_type as u8], spec.nft_name.as_bytes(), spec.symbol.as_bytes()],
+                &crate::ID,
+            );
+            AccountMeta::new(nft, false)
+        }));
+        let instruction = Instruction {
+            program_id: crate::ID,
+            accounts,
+            data: crate::instruction::InitializeCatalog { specs: batch.to_vec() }.data(),
+        };
+        solana_sdk::transaction::Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&admin.pubkey()),
+            &[&admin],
+            solana_sdk::hash::Hash::new_unique(),
+        )
+    }
+
+    #[test]
+    fn catalog_batches_fit_one_transaction_each() {
+        let specs: Vec<CardTierSpec> = (0..12)
+            .map(|i| catalog_spec(&format!("{:0>32}", i), MAX_METADATA_URI_LEN))
+            .collect();
+        let batches = CardTierSpec::catalog_batches(specs).unwrap();
+
+        // Twelve full-length tiers never fit one transaction.
+        assert!(batches.len() > 1);
+        assert_eq!(batches.iter().map(Vec::len).sum::<usize>(), 12);
+        for batch in &batches {
+            assert!(!batch.is_empty() && batch.len() <= MAX_CATALOG_SPECS);
+            let size = bincode::serialize(&catalog_transaction(batch)).unwrap().len();
+            assert!(size <= MAX_TRANSACTION_SIZE, "{} byte transaction", size);
+        }
+        assert_eq!(batches[0][0].nft_name, format!("{:0>32}", 0));
+        assert_eq!(batches.last().unwrap().last().unwrap().nft_name, format!("{:0>32}", 11));
+
+        let short: Vec<CardTierSpec> = (0..12).map(|i| catalog_spec(&format!("{:0>2}", i), 0)).collect();
+        let short_batches = CardTierSpec::catalog_batches(short).unwrap();
+        assert_eq!(short_batches.len(), 12 / MAX_CATALOG_SPECS);
+        for batch in &short_batches {
+            assert!(bincode::serialize(&catalog_transaction(batch)).unwrap().len() <= MAX_TRANSACTION_SIZE);
+        }
+    }
+
+    #[test]
+    fn update_accepts_current_and_legacy_nft_addresses() {
+        let (current, _) = Pubkey::find_program_address(&[b"nft", &[CustodyType::Custodial as u8], b"gold", b"GLD"], &crate::ID);
+        let (legacy, _) = Pubkey::find_program_address(&[b"nft", b"gold", b"GLD"], &crate::ID);
+
+        assert!(is_nft_address(&current, CustodyType::Custodial, "gold", "GLD"));
+        assert!(is_nft_address(&legacy, CustodyType::Custodial, "gold", "GLD"));
+        assert!(!is_nft_address(&current, CustodyType::NonCustodial, "gold", "GLD"));
+        assert!(!is_nft_address(&legacy, CustodyType::Custodial, "silver", "GLD"));
+    }
+
+    fn investment_fixture(discriminator: [u8; 8], investor: Pubkey, nft: Pubkey, amount: u64) -> Vec<u8> {
+        let mut body = investor.to_bytes().to_vec();
+        body.extend(nft.to_bytes());
+        body.extend(amount.to_le_bytes());
+        fixture(discriminator, body, migration::CustodialInvestmentRev1::LEN)
+    }
+
+    #[test]
+    fn migrates_original_investments_as_empty_positions() {
+        let (investor, nft) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+        let data = investment_fixture(CustodialInvestment::DISCRIMINATOR, investor, nft, 5_000);
+        let migrated = migration::upgrade_fixed_account(&data).unwrap();
+        assert_eq!(migrated.len(), CustodialInvestment::LEN);
+        let custodial = CustodialInvestment::try_deserialize(&mut &migrated[..]).unwrap();
+        assert_eq!(custodial.version, ACCOUNT_VERSION);
+        assert_eq!((custodial.investor, custodial.nft), (investor, nft));
+        assert_eq!((custodial.amount, custodial.reward_debt, custodial.unlock_at), (0, 0, 0));
+
+        let data = investment_fixture(NonCustodialInvestment::DISCRIMINATOR, investor, nft, 5_000);
+        let migrated = migration::upgrade_fixed_account(&data).unwrap();
+        assert_eq!(migrated.len(), NonCustodialInvestment::LEN);
+        let fractional = NonCustodialInvestment::try_deserialize(&mut &migrated[..]).unwrap();
+        assert_eq!((fractional.investor, fractional.nft), (investor, nft));
+        assert_eq!((fractional.amount, fractional.pending_income), (0, 0));
+    }
+
+    #[test]
+    fn migrates_original_fractional_pool_as_sold() {
+        let (parent_nft, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut body = parent_nft.to_bytes().to_vec();
+        body.extend(mint.to_bytes());
+        body.extend(400u64.to_le_bytes());
+        body.extend(1_000u64.to_le_bytes());
+        let data = fixture(FractionalNft::DISCRIMINATOR, body, migration::FractionalNftRev1::LEN);
+
+        let migrated = migration::upgrade_fixed_account(&data).unwrap();
+        assert_eq!(migrated.len(), FractionalNft::LEN);
+        let pool = FractionalNft::try_deserialize(&mut &migrated[..]).unwrap();
+        assert_eq!((pool.parent_nft, pool.mint), (parent_nft, mint));
+        assert_eq!((pool.total_supply, pool.supply_cap, pool.sold), (400, 1_000, 400));
+    }
+
+    #[test]
+    fn migrates_original_voter_record_as_zero_weight_abstention() {
+        let voter = Pubkey::new_unique();
+        let mut body = voter.to_bytes().to_vec();
+        body.extend(3u64.to_le_bytes());
+        let data = fixture(VoterRecord::DISCRIMINATOR, body, migration::VoterRecordRev1::LEN);
+
+        let migrated = migration::upgrade_fixed_account(&data).unwrap();
+        assert_eq!(migrated.len(), VoterRecord::LEN);
+        let record = VoterRecord::try_deserialize(&mut &migrated[..]).unwrap();
+        assert_eq!((record.voter, record.proposal_id), (voter, 3));
+        assert!(record.choice == VoteChoice::Abstain);
+        assert_eq!((record.weight, record.delegated_weight), (0, 0));
+    }
+
+    #[test]
+    fn inserts_version_into_fixed_accounts_missing_only_the_byte() {
+        let mut body = vec![0u8; VeLock::LEN - 9];
+        body[..32].copy_from_slice(&Pubkey::new_unique().to_bytes());
+        let data = fixture(VeLock::DISCRIMINATOR, body.clone(), VeLock::LEN - 1);
+
+        let migrated = migration::upgrade_fixed_account(&data).unwrap();
+        assert_eq!(migrated.len(), VeLock::LEN);
+        assert_eq!(migrated[..8], VeLock::DISCRIMINATOR);
+        assert_eq!(migrated[8], ACCOUNT_VERSION);
+        assert_eq!(migrated[9..], body[..]);
+
+        expect_error(migration::upgrade_fixed_account(&migrated), CustomError::AlreadyMigrated);
+        let data = fixture(VoterRecord::DISCRIMINATOR, Vec::new(), VoterRecord::LEN - 2);
+        expect_error(migration::upgrade_fixed_account(&data), CustomError::UnknownAccountLayout);
+    }
+
+    #[test]
+    fn migrates_original_rewards_config_with_its_new_vault() {
+        let (admin, reward_mint, reward_vault) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut body = crate::ID.to_bytes().to_vec();
+        body.extend(admin.to_bytes());
+        body.extend(reward_mint.to_bytes());
+        body.extend(86_400u64.to_le_bytes());
+        body.extend(1_000u64.to_le_bytes());
+        body.push(1);
+        body.extend(10i64.to_le_bytes());
+        body.extend(20i64.to_le_bytes());
+        let data = fixture(RewardsConfig::DISCRIMINATOR, body, migration::RewardsConfigRev1::LEN);
+
+        let migrated = migration::upgrade_rewards_config(&data, reward_vault, 254).unwrap();
+        assert_eq!(migrated.version, ACCOUNT_VERSION);
+        assert_eq!((migrated.admin_authority, migrated.reward_token_mint), (admin, reward_mint));
+        assert_eq!((migrated.distribution_interval, migrated.max_rewards_per_user), (86_400, 1_000));
+        assert!(migrated.is_active);
+        assert_eq!((migrated.created_at, migrated.updated_at), (10, 20));
+        assert_eq!((migrated.reward_vault, migrated.outstanding_income, migrated.bump), (reward_vault, 0, 254));
+
+        expect_error(migration::upgrade_fixed_account(&data), CustomError::UnknownAccountLayout);
+    }
 }